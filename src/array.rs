@@ -0,0 +1,124 @@
+use core::num::NonZeroUsize;
+use crate::trie::patricia::RadixTrie;
+use crate::trie::storage::ArrayStorage;
+use crate::trie::common::{CapacityError, Leaf};
+
+use crate::prefix::*;
+
+/// A set of Ip prefixes based on a radix binary trie stored in fixed size arrays
+///
+/// This set behaves as [`crate::RTrieSet`] but never allocates, which is
+/// suitable for small sets (as tiny ACLs). Its capacity `N` counts the root prefix,
+/// so at most `N-1` other prefixes could be inserted.
+///
+/// Notice that some internal nodes are not freed when removing a prefix so
+/// the capacity could be exhausted by many insertions and removals.
+///
+/// # Example
+/// ```
+/// # use iptrie::*;
+/// use std::net::Ipv4Addr;
+/// let mut trie = ArrayRTrieSet::<Ipv4Prefix,3>::new();
+///
+/// assert_eq!( trie.insert("1.1.0.0/16".parse().unwrap()), Ok(true));
+/// assert_eq!( trie.insert("1.1.1.0/24".parse().unwrap()), Ok(true));
+/// assert_eq!( trie.insert("1.1.1.0/24".parse().unwrap()), Ok(false));
+/// assert_eq!( trie.insert("2.0.0.0/8".parse().unwrap()), Err(CapacityError));
+///
+/// assert_eq!( trie.lookup(&Ipv4Addr::new(1,1,1,1)).to_string(), "1.1.1.0/24");
+/// ```
+pub struct ArrayRTrieSet<P: IpPrefix, const N: usize>(pub(crate) RadixTrie<P,(),ArrayStorage<N>>);
+
+impl<P:IpRootPrefix, const N: usize> ArrayRTrieSet<P,N>
+{
+    /// Creates a new set which contains the root prefix.
+    ///
+    /// # Panics
+    /// Panics if `N` equals zero (there is no room for the root prefix).
+    #[inline]
+    pub fn new() -> Self { Self(RadixTrie::new((), N)) }
+}
+
+impl<P:IpRootPrefix, const N: usize> Default for ArrayRTrieSet<P,N>
+{
+    #[inline]
+    fn default() -> Self { Self::new() }
+}
+
+impl<P:IpPrefix, const N: usize> Clone for ArrayRTrieSet<P,N>
+{
+    #[inline]
+    fn clone(&self) -> Self { Self(self.0.clone()) }
+}
+
+impl<P:IpPrefix, const N: usize> ArrayRTrieSet<P,N>
+{
+    /// Returns the size of the set.
+    ///
+    /// Notice that it never equals zero since the top prefix is
+    /// always present in the set.
+    #[inline]
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> NonZeroUsize { self.0.len() }
+
+    /// Checks if the set contains only the root prefix.
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.0.len().get() == 1 }
+
+    /// Inserts a new element in the set.
+    ///
+    /// If the specified element already exists in the set, `false` is returned.
+    /// If there is no more room for it, an error is returned.
+    pub fn insert(&mut self, k: P) -> Result<bool, CapacityError>
+    {
+        if self.contains(&k) {
+            Ok(false)
+        } else if self.0.leaves.0.is_full() || self.0.branching.0.is_full() {
+            // an insertion adds one leaf and at most one branching
+            Err(CapacityError)
+        } else {
+            Ok(self.0.insert(k,()).is_none())
+        }
+    }
+
+    /// Checks if an element is present (exact match).
+    #[inline]
+    pub fn contains<Q>(&self, k: &Q) -> bool
+        where
+            Q: IpPrefix<Addr=P::Addr>,
+            P: IpPrefixCovering<Q>
+    {
+        self.0.get(k).is_some()
+    }
+
+    /// Removes a previously inserted prefix (exact match).
+    ///
+    /// Returns `false` is the element was not present in the set
+    /// and `true` if the removal is effective.
+    #[inline]
+    pub fn remove<Q>(&mut self, k: &Q) -> bool
+        where
+            Q: IpPrefix<Addr=P::Addr>,
+            P: IpPrefixCovering<Q>
+    {
+        self.0.remove(k).is_some()
+    }
+
+    /// Gets the longest prefix which matches the given key.
+    ///
+    /// As the top prefix always matches, it never fails.
+    #[inline]
+    pub fn lookup<Q>(&self, k: &Q) -> &P
+        where
+            Q: IpPrefix<Addr=P::Addr>,
+            P: IpPrefixCovering<Q>
+    {
+        self.0.lookup(k).0
+    }
+
+    /// Iterates over all the prefixes of this set.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item=&P> + '_ {
+        self.0.iter().map(Leaf::prefix)
+    }
+}
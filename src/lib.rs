@@ -1,12 +1,24 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
 mod trie;
 mod map;
 mod set;
+mod multimap;
+mod priority;
+mod array;
 
 mod prefix;
 
 pub use map::*;
 pub use set::*;
+pub use multimap::*;
+pub use priority::*;
+pub use array::*;
 pub use prefix::*;
+pub use trie::common::CapacityError;
+pub use trie::lctrie::{CompressError, CompressionReport};
 
 #[cfg(feature = "graphviz")]
 pub use trie::graphviz::DotWriter;
@@ -1,13 +1,68 @@
-use std::num::NonZeroUsize;
+use core::num::NonZeroUsize;
+use core::slice;
+use alloc::vec::Vec;
 use crate::trie::patricia::RadixTrie;
-use crate::trie::lctrie::LevelCompressedTrie;
+use crate::trie::lctrie::{CompressError, CompressionReport, LevelCompressedTrie};
+use crate::trie::common::CapacityError;
 use crate::set::*;
 
 use crate::prefix::*;
 
 #[cfg(feature = "graphviz")] pub use crate::trie::graphviz::DotWriter;
 #[cfg(feature = "graphviz")] use std::fmt::Display;
-use crate::trie::common::Leaf;
+use crate::trie::common::{cmp_prefixes, Leaf, LeafIndex};
+
+/// An iterator over the entries of a map
+///
+/// See [`RTrieMap::iter`] and [`LCTrieMap::iter`].
+pub struct MapIter<'a,K,V>(slice::Iter<'a, Leaf<K,V>>);
+
+impl<'a,K,V> Iterator for MapIter<'a,K,V> {
+    type Item = (&'a K, &'a V);
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> { self.0.next().map(Leaf::get) }
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) { self.0.size_hint() }
+}
+
+impl<K,V> ExactSizeIterator for MapIter<'_,K,V> {}
+
+/// An iterator over the entries of a map with a mutable access to values
+///
+/// See [`RTrieMap::iter_mut`] and [`LCTrieMap::iter_mut`].
+pub struct MapIterMut<'a,K,V>(slice::IterMut<'a, Leaf<K,V>>);
+
+impl<'a,K,V> Iterator for MapIterMut<'a,K,V> {
+    type Item = (&'a K, &'a mut V);
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> { self.0.next().map(Leaf::get_mut) }
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) { self.0.size_hint() }
+}
+
+impl<K,V> ExactSizeIterator for MapIterMut<'_,K,V> {}
+
+/// The differences between two maps
+///
+/// See [`RTrieMap::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrieDiff<'a,K,V> {
+    /// The entries whose prefix is only present in the first map
+    pub removed: Vec<(&'a K, &'a V)>,
+    /// The entries whose prefix is only present in the second map
+    pub added: Vec<(&'a K, &'a V)>,
+    /// The prefixes present in both maps but with different values
+    /// (first the value of the first map, then the one of the second map)
+    pub changed: Vec<(&'a K, &'a V, &'a V)>,
+}
+
+impl<K,V> TrieDiff<'_,K,V> {
+    /// Checks if the two maps hold the same entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.added.is_empty() && self.changed.is_empty()
+    }
+}
 
 /// A map of Ip prefixes based on a radix binary trie
 #[derive(Clone)]
@@ -19,6 +74,9 @@ pub type Ipv4RTrieMap<V> = RTrieMap<Ipv4Prefix,V>;
 pub type Ipv6RTrieMap<V> = RTrieMap<Ipv6Prefix,V>;
 
 /// A map of Ip prefixes based on a level-compressed trie
+///
+/// This map is `Send` (resp. `Sync`) as soon as its keys and values are `Send` (resp. `Sync`),
+/// so it could be shared between threads to perform concurrent lookups.
 pub struct LCTrieMap<K,V>(pub(crate) LevelCompressedTrie<K,V>);
 
 /// Convenient alias for LC-Trie map of Ipv4 prefixes
@@ -32,6 +90,7 @@ impl<K:IpRootPrefix,V:Default> RTrieMap<K,V>
     /// Create a new map.
     ///
     /// The root prefix is associated with the default value of `V`.
+    /// If `V` does not implement [`Default`], use [`Self::with_root`].
     #[inline]
     pub fn new() -> Self { Self::default() }
 
@@ -40,6 +99,16 @@ impl<K:IpRootPrefix,V:Default> RTrieMap<K,V>
     /// The root prefix is associated with the default value of `V`.
     #[inline]
     pub fn with_capacity(capacity:usize) -> Self { Self::with_root_and_capacity(V::default(), capacity)}
+
+    /// Create a new map with initial capacities for the entries and the branching nodes.
+    ///
+    /// By default (see [`Self::with_capacity`]), there is room for half
+    /// as many branching nodes as entries, which could be too few for sparse maps.
+    /// The root prefix is associated with the default value of `V`.
+    #[inline]
+    pub fn with_capacities(leaves: usize, branching: usize) -> Self {
+        Self(RadixTrie::with_capacities(V::default(), leaves, branching))
+    }
 }
 
 impl<K:IpRootPrefix,V:Default> Default for RTrieMap<K,V>
@@ -51,10 +120,45 @@ impl<K:IpRootPrefix,V> RTrieMap<K,V>
 {
     /// Creates a new trie map with the specified value associated to the
     /// root prefix.
+    ///
+    /// Unlike [`Self::new`], the value type is not required to implement [`Default`].
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv4Addr;
+    /// #[derive(Debug, PartialEq)]
+    /// enum Action { Drop, Accept }
+    ///
+    /// let mut trie = Ipv4RTrieMap::with_root(Action::Drop);
+    /// trie.insert("1.1.0.0/16".parse().unwrap(), Action::Accept);
+    ///
+    /// assert_eq!( trie.lookup(&Ipv4Addr::new(1,1,1,1)).1, &Action::Accept);
+    /// assert_eq!( trie.lookup(&Ipv4Addr::new(2,1,1,1)).1, &Action::Drop);
+    /// ```
     #[inline]
     pub fn with_root(root: V) -> Self { Self::with_root_and_capacity(root, 1000) }
 
+    /// Creates a new trie map with the specified value associated to the
+    /// root prefix.
+    ///
+    /// This is an alias of [`Self::with_root`].
+    #[inline]
+    pub fn from_root(root: V) -> Self { Self::with_root(root) }
+
     /// Creates a new trie map with a initial capacity.
+    ///
+    /// As for [`Self::with_root`], the value type is not required to implement [`Default`].
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// struct NoDefault(u32);
+    ///
+    /// let trie = Ipv4RTrieMap::with_root_and_capacity(NoDefault(42), 100);
+    /// assert_eq!( trie.len().get(), 1);
+    /// assert_eq!( trie.lookup(&Ipv4Prefix::root()).1.0, 42);
+    /// ```
     #[inline]
     pub fn with_root_and_capacity(root: V, capacity: usize) -> Self {
         Self(RadixTrie::new(root, capacity))
@@ -80,6 +184,10 @@ impl<K:IpPrefix,V> RTrieMap<K,V>
     #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> NonZeroUsize { self.0.len() }
 
+    /// Checks if the map contains only the root prefix.
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.0.len().get() == 1 }
+
     /// Compress this Patricia trie in a LC-Trie.
     ///
     /// For lookup algorithms, a Patricia trie performs unit bit checking and LC-Trie
@@ -88,6 +196,52 @@ impl<K:IpPrefix,V> RTrieMap<K,V>
     #[inline]
     pub fn compress(self) -> LCTrieMap<K,V> { LCTrieMap(LevelCompressedTrie::new(self.0)) }
 
+    /// Compress this Patricia trie in a LC-Trie and transforms the values.
+    ///
+    /// Each value is replaced by the result of the closure applied
+    /// to the entry (the root one included), which allows to precompute
+    /// some data at compression time.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv4Addr;
+    /// let trie = Ipv4RTrieMap::from_iter([("1.1.0.0/16".parse().unwrap(), 7)]);
+    ///
+    /// let lctrie = trie.compress_map(|k, v| format!("{} via {}", k, v));
+    /// assert_eq!( lctrie.lookup(&Ipv4Addr::new(1,1,1,1)).1, "1.1.0.0/16 via 7");
+    /// assert_eq!( lctrie.lookup(&Ipv4Addr::new(2,1,1,1)).1, "0.0.0.0/0 via 0");
+    /// ```
+    #[inline]
+    pub fn compress_map<W, F: FnMut(&K, &V) -> W>(self, f: F) -> LCTrieMap<K,W> {
+        LCTrieMap(LevelCompressedTrie::new(self.0.into_map(f)))
+    }
+
+    /// Compress this Patricia trie in a LC-Trie and checks the result.
+    ///
+    /// See [`RTrieSet::try_compress`] for the performed checks.
+    pub fn try_compress(self) -> Result<LCTrieMap<K,V>, CompressError>
+    {
+        let lctrie = LevelCompressedTrie::new(self.0);
+        lctrie.check()?;
+        Ok(LCTrieMap(lctrie))
+    }
+
+    /// Predicts the memory size (in bytes) of the compressed version of this trie.
+    ///
+    /// The compressed nodes are sized by the same pass as the compression itself
+    /// (which is not performed) so the result is exactly the one given by
+    /// [`LCTrieMap::memory_bytes`] after the compression.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieMap::from_iter([("1.1.0.0/16".parse::<Ipv4Prefix>().unwrap(), 1), ("1.1.1.0/24".parse().unwrap(), 2)]);
+    /// let estimated = trie.estimated_compressed_bytes();
+    /// assert_eq!( trie.compress().memory_bytes(), estimated);
+    /// ```
+    pub fn estimated_compressed_bytes(&self) -> usize { LevelCompressedTrie::estimated_bytes(&self.0) }
+
     #[inline]
     pub fn shrink_to_fit(&mut self) { self.0.shrink_to_fit() }
 
@@ -115,6 +269,24 @@ impl<K:IpPrefix,V> RTrieMap<K,V>
         self.0.insert(k, v)
     }
 
+    /// Inserts a new element in the map, checking the capacity of the trie.
+    ///
+    /// It behaves as [`Self::insert`] but returns an error, instead of panicking,
+    /// if the trie has reached its maximal number of nodes (about 2<sup>31</sup>).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut trie = Ipv4RTrieMap::with_root(0);
+    ///
+    /// assert_eq!( trie.try_insert("1.1.0.0/16".parse().unwrap(), 1), Ok(None));
+    /// assert_eq!( trie.try_insert("1.1.0.0/16".parse().unwrap(), 2), Ok(Some(1)));
+    /// ```
+    #[inline]
+    pub fn try_insert(&mut self, k: K, v: V) -> Result<Option<V>, CapacityError> {
+        self.0.try_insert(k, v)
+    }
+
     /// Gets the value associated with an exact match of the key.
     ///
     /// To access to the longest prefix match, use [`Self::lookup`].
@@ -148,6 +320,34 @@ impl<K:IpPrefix,V> RTrieMap<K,V>
         self.0.get(k).map(|(_,v)| v)
     }
 
+    /// Gets the value associated with an exact match of the key
+    /// or, if none, the value associated to the root prefix.
+    ///
+    /// Unlike [`Self::lookup`], the intermediate prefixes which cover the key
+    /// are not considered.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut trie = RTrieMap::with_root(42);
+    ///
+    /// let ip20 = "1.1.1.1/20".parse::<Ipv4Prefix>().unwrap();
+    /// let ip22 = "1.1.1.1/22".parse::<Ipv4Prefix>().unwrap();
+    /// trie.insert(ip20, 20);
+    ///
+    /// assert_eq!( trie.get_or_default(&ip20), &20);
+    /// assert_eq!( trie.get_or_default(&ip22), &42);
+    /// assert_eq!( trie.lookup(&ip22).1, &20);
+    /// ```
+    #[inline]
+    pub fn get_or_default<Q>(&self, k: &Q) -> &V
+        where
+            Q: IpPrefix<Addr=K::Addr>,
+            K: IpPrefixCovering<Q>
+    {
+        self.get(k).unwrap_or_else(|| self.0.leaves[LeafIndex::root_leaf()].get().1)
+    }
+
     /// Gets a mutable access to the value associated with an exact match of the key.
     ///
     /// To access to the longest prefix match, use [`Self::lookup_mut`].
@@ -180,6 +380,27 @@ impl<K:IpPrefix,V> RTrieMap<K,V>
         self.0.get_mut(k).map(|(_,v)| v)
     }
 
+    /// Gets a mutable access to the value of a prefix (exact match),
+    /// inserting the value computed by `f` if the prefix is absent.
+    ///
+    /// The trie is walked only once and `f` is called only if needed.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut trie = Ipv4RTrieMap::new();
+    /// let ip = "1.1.0.0/16".parse::<Ipv4Prefix>().unwrap();
+    ///
+    /// *trie.get_or_insert_with_mut(ip, || 10) += 1;
+    /// *trie.get_or_insert_with_mut(ip, || unreachable!()) += 1;
+    /// assert_eq!( trie.get(&ip), Some(&12));
+    /// ```
+    #[inline]
+    pub fn get_or_insert_with_mut<F: FnOnce() -> V>(&mut self, k: K, f: F) -> &mut V
+    {
+        self.0.get_or_insert_with(k, f)
+    }
+
     /// Removes a previously inserted prefix (exact match).
     /// # Panic
     /// Panics if trying to remove the root prefix.
@@ -210,6 +431,32 @@ impl<K:IpPrefix,V> RTrieMap<K,V>
         self.0.remove(k)
     }
 
+    /// Removes all the given prefixes (exact match).
+    ///
+    /// The absent prefixes are skipped and, unlike [`Self::remove`],
+    /// the root prefix is silently ignored.
+    /// Returns the number of entries which were effectively removed.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut trie = Ipv4RTrieMap::from_iter(["10.0.0.0/8", "10.1.0.0/16", "1.1.1.0/24"].map(|p| (p.parse().unwrap(), 1)));
+    ///
+    /// let removed = trie.remove_all(["10.1.0.0/16", "10.2.0.0/16", "0.0.0.0/0"].map(|p| p.parse::<Ipv4Prefix>().unwrap()));
+    /// assert_eq!( removed, 1);
+    /// assert_eq!( trie.len().get(), 3);
+    /// ```
+    pub fn remove_all<Q, I: IntoIterator<Item=Q>>(&mut self, iter: I) -> usize
+        where
+            Q: IpPrefix<Addr=K::Addr>,
+            K: IpPrefixCovering<Q>
+    {
+        iter.into_iter()
+            .filter(|k| k.len() > 0) // never remove the root prefix
+            .filter(|k| self.remove(k).is_some())
+            .count()
+    }
+
     /// Gets the entry associated with the longest prefix match of the key.
     ///
     /// As the top prefix always matches, it never fails.
@@ -272,16 +519,208 @@ impl<K:IpPrefix,V> RTrieMap<K,V>
 
     /// Iterates over all the entries.
     ///
+    /// The root entry comes first but the order of the others is not specified
+    /// and may change in future versions. For a documented order,
+    /// use [`Self::iter_insertion_order`].
+    ///
     /// For a mutable access of values, use [`Self::iter_mut`]
     #[inline]
-    pub fn iter(&self) -> impl Iterator<Item=(&K,&V)> + '_ {
-        self.0.iter().map(Leaf::get)
+    pub fn iter(&self) -> MapIter<'_,K,V> {
+        MapIter(self.0.leaves.0.iter())
+    }
+
+    /// Iterates over all the entries, except the root one.
+    ///
+    /// The root entry is implicit: it is always in the map, even if not inserted.
+    /// The order is the one of [`Self::iter`].
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieMap::from([("10.0.0.0/8".parse().unwrap(), 8), ("1.1.0.0/16".parse().unwrap(), 16)]);
+    /// assert_eq!( trie.iter().count(), 3);
+    /// assert_eq!( trie.iter_non_root().map(|(_,v)| *v).collect::<Vec<_>>(), [8, 16]);
+    /// ```
+    #[inline]
+    pub fn iter_non_root(&self) -> MapIter<'_,K,V> {
+        MapIter(self.0.leaves.0[1..].iter()) // the root entry is always the first one
+    }
+
+    /// Iterates over all the entries of this map in insertion order.
+    ///
+    /// The root entry comes first, then the others in insertion order.
+    /// Notice that removing an entry moves the last one at its place.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut trie = Ipv4RTrieMap::with_root(0);
+    /// [("10.0.0.0/8", 8), ("1.1.0.0/16", 16), ("10.1.0.0/16", 17)].iter()
+    ///     .for_each(|(p,v)| { trie.insert(p.parse().unwrap(), *v); });
+    ///
+    /// assert_eq!( trie.iter_insertion_order().map(|(_,v)| *v).collect::<Vec<_>>(), [0, 8, 16, 17]);
+    ///
+    /// trie.remove(&"10.0.0.0/8".parse::<Ipv4Prefix>().unwrap());
+    /// assert_eq!( trie.iter_insertion_order().map(|(_,v)| *v).collect::<Vec<_>>(), [0, 17, 16]);
+    /// ```
+    #[inline]
+    pub fn iter_insertion_order(&self) -> MapIter<'_,K,V> {
+        MapIter(self.0.leaves.0.iter())
     }
 
     /// Iterates over all the entries with a mutable access to values.
     #[inline]
-    pub fn iter_mut(&mut self) -> impl Iterator<Item=(&K,&mut V)> + '_ {
-        self.0.iter_mut().map(Leaf::get_mut)
+    pub fn iter_mut(&mut self) -> MapIterMut<'_,K,V> {
+        MapIterMut(self.0.leaves.0.iter_mut())
+    }
+
+    /// Removes all the entries of this map (except the root one) and iterates over them.
+    ///
+    /// The allocated memory is kept and the map remains usable.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut trie = Ipv4RTrieMap::from([("1.1.0.0/16".parse().unwrap(), 16)]);
+    /// assert_eq!( trie.drain().collect::<Vec<_>>(), [("1.1.0.0/16".parse().unwrap(), 16)]);
+    /// assert!( trie.is_empty() );
+    /// ```
+    #[inline]
+    pub fn drain(&mut self) -> impl Iterator<Item=(K,V)> + '_ {
+        self.0.drain()
+    }
+
+    /// Writes the hierarchy of the entries of this map as an indented text tree.
+    ///
+    /// Each entry is written on its own line (sorted by address) as `prefix => value`
+    /// and is indented according to the number of prefixes which cover it.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieMap::from([
+    ///     ("1.1.0.0/16".parse().unwrap(), 16),
+    ///     ("1.1.1.0/24".parse().unwrap(), 24)]);
+    ///
+    /// let mut out = Vec::new();
+    /// trie.write_tree(&mut out).unwrap();
+    /// assert_eq!( String::from_utf8(out).unwrap(), "0.0.0.0/0 => 0\n  1.1.0.0/16 => 16\n    1.1.1.0/24 => 24\n");
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_tree<W: std::io::Write>(&self, out: &mut W) -> std::io::Result<()>
+        where K: core::fmt::Display, V: core::fmt::Display
+    {
+        self.0.hierarchy()
+            .into_iter()
+            .try_for_each(|(depth, l)| {
+                let (k, v) = self.0.leaves[l].get();
+                writeln!(out, "{:indent$}{} => {}", "", k, v, indent = 2*depth)
+            })
+    }
+
+    /// Compares this map with another one.
+    ///
+    /// The prefixes are compared by range (as for [`Self::get`]).
+    /// The root prefix is present in both maps, so it could only be changed.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let before = Ipv4RTrieMap::from([
+    ///     ("10.0.0.0/8".parse().unwrap(), 1),
+    ///     ("10.1.0.0/16".parse().unwrap(), 2) ]);
+    /// let after = Ipv4RTrieMap::from([
+    ///     ("10.0.0.0/8".parse().unwrap(), 3),
+    ///     ("10.2.0.0/16".parse().unwrap(), 2) ]);
+    ///
+    /// let diff = before.diff(&after);
+    /// assert_eq!( diff.removed, [(&"10.1.0.0/16".parse().unwrap(), &2)]);
+    /// assert_eq!( diff.added, [(&"10.2.0.0/16".parse().unwrap(), &2)]);
+    /// assert_eq!( diff.changed, [(&"10.0.0.0/8".parse().unwrap(), &1, &3)]);
+    /// ```
+    pub fn diff<'a>(&'a self, other: &'a RTrieMap<K,V>) -> TrieDiff<'a,K,V>
+        where V: PartialEq
+    {
+        let mut diff = TrieDiff { removed: Vec::new(), added: Vec::new(), changed: Vec::new() };
+        self.iter()
+            .for_each(|(k, v)| match other.get(k) {
+                None => diff.removed.push((k, v)),
+                Some(w) if v != w => diff.changed.push((k, v, w)),
+                Some(_) => {}
+            });
+        diff.added = other.iter()
+            .filter(|(k, _)| self.get(*k).is_none())
+            .collect();
+        diff
+    }
+
+    /// Converts this map into a vector of its entries sorted by network address, then by length.
+    ///
+    /// The root entry is included (and so comes first).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieMap::from([
+    ///     ("10.1.0.0/16".parse().unwrap(), 16),
+    ///     ("10.0.0.0/8".parse().unwrap(), 8),
+    ///     ("1.1.1.0/24".parse().unwrap(), 24) ]);
+    ///
+    /// let entries = trie.into_sorted_vec();
+    /// assert_eq!( entries.iter().map(|(k,v)| format!("{k}={v}")).collect::<Vec<_>>(),
+    ///     ["0.0.0.0/0=0", "1.1.1.0/24=24", "10.0.0.0/8=8", "10.1.0.0/16=16"]);
+    /// ```
+    pub fn into_sorted_vec(self) -> Vec<(K,V)>
+    {
+        let mut entries = self.0.leaves.into_iter().collect::<Vec<_>>();
+        entries.sort_by(|(a,_), (b,_)| cmp_prefixes(a, b));
+        entries
+    }
+
+    /// Groups the prefixes of this map by value.
+    ///
+    /// Each distinct value is associated to the list of the prefixes
+    /// which carry it (the root prefix included).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieMap::from([
+    ///     ("1.1.0.0/16".parse().unwrap(), 64500),
+    ///     ("1.2.0.0/16".parse().unwrap(), 64501),
+    ///     ("1.3.0.0/16".parse().unwrap(), 64500) ]);
+    ///
+    /// let groups = trie.group_by_value();
+    /// assert_eq!( groups.len(), 3);
+    /// assert_eq!( groups[&64500].iter().map(|p| p.to_string()).collect::<Vec<_>>(), ["1.1.0.0/16", "1.3.0.0/16"]);
+    /// assert_eq!( groups[&0].iter().map(|p| p.to_string()).collect::<Vec<_>>(), ["0.0.0.0/0"]);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn group_by_value(&self) -> std::collections::HashMap<&V, Vec<&K>>
+        where V: Eq + core::hash::Hash
+    {
+        let mut groups = std::collections::HashMap::<_, Vec<_>>::new();
+        self.iter().for_each(|(k,v)| groups.entry(v).or_default().push(k));
+        groups
+    }
+
+    /// Counts the distinct values of this map (the value of the root prefix included).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieMap::from([
+    ///     ("1.1.0.0/16".parse().unwrap(), 64500),
+    ///     ("1.2.0.0/16".parse().unwrap(), 64501),
+    ///     ("1.3.0.0/16".parse().unwrap(), 64500) ]);
+    ///
+    /// assert_eq!( trie.distinct_values(), 3); // with 0 for the root prefix
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn distinct_values(&self) -> usize
+        where V: Eq + core::hash::Hash
+    {
+        self.iter().map(|(_,v)| v).collect::<std::collections::HashSet<_>>().len()
     }
 
     /// Gets a set of copy of all the keys in a trie set.
@@ -290,9 +729,65 @@ impl<K:IpPrefix,V> RTrieMap<K,V>
     {
         RTrieSet(self.0.map(|_| ()))
     }
+
+    /// Gets a set of copy of the keys whose entry satisfies the predicate.
+    ///
+    /// The root prefix is always included in the returned set.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieMap::from([
+    ///     ("1.1.0.0/16".parse().unwrap(), 16),
+    ///     ("1.1.1.0/24".parse().unwrap(), 24),
+    ///     ("2.0.0.0/8".parse().unwrap(), 8) ]);
+    ///
+    /// let set = trie.prefixes_where(|_,v| *v >= 16);
+    /// assert_eq!( set.iter().map(|p| p.to_string()).collect::<Vec<_>>(), ["0.0.0.0/0", "1.1.0.0/16", "1.1.1.0/24"]);
+    /// ```
+    pub fn prefixes_where<F: FnMut(&K,&V)->bool>(&self, mut f: F) -> RTrieSet<K>
+        where K: IpRootPrefix
+    {
+        self.iter()
+            .filter(|(k,v)| f(k,v))
+            .map(|(k,_)| *k)
+            .collect()
+    }
 }
 
 
+impl<'a,K:IpPrefix,V> IntoIterator for &'a RTrieMap<K,V>
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = MapIter<'a,K,V>;
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter { self.iter() }
+}
+
+impl<'a,K:IpPrefix,V> IntoIterator for &'a mut RTrieMap<K,V>
+{
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = MapIterMut<'a,K,V>;
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter { self.iter_mut() }
+}
+
+impl<'a,K:IpPrefix,V> IntoIterator for &'a LCTrieMap<K,V>
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = MapIter<'a,K,V>;
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter { self.iter() }
+}
+
+impl<'a,K:IpPrefix,V> IntoIterator for &'a mut LCTrieMap<K,V>
+{
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = MapIterMut<'a,K,V>;
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter { self.iter_mut() }
+}
+
 impl<K:IpPrefix,V> Extend<(K, V)> for RTrieMap<K,V>
 {
     fn extend<I: IntoIterator<Item=(K,V)>>(&mut self, iter: I)
@@ -311,6 +806,28 @@ impl<K:IpRootPrefix,V:Default> FromIterator<(K, V)> for RTrieMap<K,V>
     }
 }
 
+impl<K:IpRootPrefix,V:Default, const N: usize> From<[(K,V); N]> for RTrieMap<K,V>
+{
+    /// Builds a map from an array of entries.
+    ///
+    /// The root prefix is associated with the default value of `V`.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieMap::from([("1.0.0.0/8".parse().unwrap(), 8)]);
+    /// assert_eq!( trie.get(&"1.0.0.0/8".parse::<Ipv4Prefix>().unwrap()), Some(&8));
+    /// ```
+    #[inline]
+    fn from(array: [(K,V); N]) -> Self { Self::from_iter(array) }
+}
+
+impl<K:IpRootPrefix,V:Default> From<Vec<(K,V)>> for RTrieMap<K,V>
+{
+    #[inline]
+    fn from(vec: Vec<(K,V)>) -> Self { Self::from_iter(vec) }
+}
+
 impl<K:IpPrefix,V> LCTrieMap<K,V>
 {
     /// Returns the size of the map.
@@ -456,25 +973,67 @@ impl<K:IpPrefix,V> LCTrieMap<K,V>
             K: IpPrefixCovering<Q>
     { self.0.lookup_mut(k) }
 
+    /// Visits all the entries in prefix order.
+    ///
+    /// Unlike [`Self::iter`], the entries are visited by walking the
+    /// compressed tree: each entry is visited once and a prefix is always
+    /// visited before the prefixes it covers.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieMap::from([
+    ///     ("1.1.1.0/24".parse().unwrap(), 24),
+    ///     ("1.1.0.0/16".parse().unwrap(), 16),
+    ///     ("1.0.0.0/8".parse().unwrap(), 8) ]);
+    /// let lctrie = trie.compress();
+    ///
+    /// let mut values = vec![];
+    /// lctrie.for_each_preorder(|_,v| values.push(*v));
+    /// assert_eq!( values, [0, 8, 16, 24]);
+    /// ```
+    #[inline]
+    pub fn for_each_preorder<F: FnMut(&K,&V)>(&self, f: F) { self.0.for_each_preorder(f) }
+
+    #[cfg(feature = "std")]
     #[inline]
     pub fn info(&self) { self.0.info() }
 
+    /// Reports how the branching nodes were merged by the compression.
+    #[inline]
+    pub fn compression_report(&self) -> CompressionReport { self.0.compression_report() }
+
+    /// Memory size (in bytes) used by the nodes and the leaves of this trie.
+    ///
+    /// The size of the data referred by the values (if any) is not counted.
+    #[inline]
+    pub fn memory_bytes(&self) -> usize { self.0.memory_bytes() }
+
     /// Iterates over all the entries.
     ///
     /// As the root prefix always exists, this iterator is never empty.
     ///
+    /// The order is the one of [`RTrieMap::iter_insertion_order`] for the map
+    /// which was compressed (it is kept as is by the compression).
+    /// For an iteration in prefix order, see [`Self::for_each_preorder`].
+    ///
     /// For a mutable access of values, use [`Self::iter_mut`]
     ///
     /// # Example
     /// ```
     /// # use iptrie::*;
-    /// let trie = Ipv4RTrieMap::with_root(42);
-    /// let lctrie = trie.compress();
+    /// let trie = Ipv4RTrieMap::from([
+    ///     ("1.1.1.0/24".parse().unwrap(), 24),
+    ///     ("1.0.0.0/8".parse().unwrap(), 8),
+    ///     ("1.1.0.0/16".parse().unwrap(), 16) ]);
+    /// let lctrie = trie.clone().compress();
     /// assert_eq!( lctrie.len().get(), lctrie.iter().count());
+    /// assert!( lctrie.iter().eq(trie.iter_insertion_order()));
+    /// assert_eq!( lctrie.iter().map(|(_,v)| *v).collect::<Vec<_>>(), [0, 24, 8, 16]);
     /// ```
     #[inline]
-    pub fn iter(&self) -> impl Iterator<Item=(&K,&V)> + '_ {
-        self.0.leaves.0.iter().map(Leaf::get)
+    pub fn iter(&self) -> MapIter<'_,K,V> {
+        MapIter(self.0.leaves.0.iter())
     }
 
     /// Iterates over all the entries with a mutable access to values.
@@ -491,8 +1050,40 @@ impl<K:IpPrefix,V> LCTrieMap<K,V>
     /// assert_eq!( lctrie.lookup(&Ipv4Prefix::root()).1, &43);
     /// ```
     #[inline]
-    pub fn iter_mut(&mut self) -> impl Iterator<Item=(&K,&mut V)> + '_ {
-        self.0.leaves.0.iter_mut().map(Leaf::get_mut)
+    pub fn iter_mut(&mut self) -> MapIterMut<'_,K,V> {
+        MapIterMut(self.0.leaves.0.iter_mut())
+    }
+
+    /// Iterates over all the values with a mutable access (the root one included).
+    ///
+    /// The structure of the compressed trie is not modified.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut lctrie = Ipv4RTrieMap::from_iter([("1.1.0.0/16".parse().unwrap(), 1)]).compress();
+    /// lctrie.values_mut().for_each(|v| *v *= 10);
+    /// assert_eq!( lctrie.iter().map(|(_,v)| *v).collect::<Vec<_>>(), [0, 10]);
+    /// ```
+    #[inline]
+    pub fn values_mut(&mut self) -> impl Iterator<Item=&mut V> + '_ {
+        self.iter_mut().map(|(_,v)| v)
+    }
+
+    /// Updates all the values (the root one included) according to their prefix.
+    ///
+    /// The structure of the compressed trie is not modified.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut lctrie = Ipv4RTrieMap::from_iter([("1.1.0.0/16".parse().unwrap(), 1)]).compress();
+    /// lctrie.update_all(|k,v| *v += k.len() as u32);
+    /// assert_eq!( lctrie.iter().map(|(_,v)| *v).collect::<Vec<_>>(), [0, 17]);
+    /// ```
+    #[inline]
+    pub fn update_all<F: FnMut(&K, &mut V)>(&mut self, mut f: F) {
+        self.iter_mut().for_each(|(k,v)| f(k,v))
     }
 
     /// Gets a set of copy of all the keys in a trie set.
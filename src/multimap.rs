@@ -0,0 +1,145 @@
+use core::num::NonZeroUsize;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::map::RTrieMap;
+
+use crate::prefix::*;
+
+/// A multimap of Ip prefixes based on a radix binary trie
+///
+/// Each prefix is associated to a list of values (for instance, the
+/// next-hops of an ECMP route). The root prefix is associated to an empty list.
+#[derive(Clone)]
+pub struct RTrieMultiMap<K,V>(pub(crate) RTrieMap<K,Vec<V>>);
+
+/// Convenient alias for radix trie multimap of Ipv4 prefixes
+pub type Ipv4RTrieMultiMap<V> = RTrieMultiMap<Ipv4Prefix,V>;
+/// Convenient alias for radix trie multimap of Ipv6 prefixes
+pub type Ipv6RTrieMultiMap<V> = RTrieMultiMap<Ipv6Prefix,V>;
+
+impl<K:IpRootPrefix,V> RTrieMultiMap<K,V>
+{
+    /// Creates a new multimap.
+    ///
+    /// The root prefix is associated with an empty list of values.
+    #[inline]
+    pub fn new() -> Self { Self::with_capacity(1000) }
+
+    /// Creates a new multimap with a initial capacity.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(RTrieMap::with_root_and_capacity(Vec::new(), capacity))
+    }
+}
+
+impl<K:IpRootPrefix,V> Default for RTrieMultiMap<K,V>
+{
+    #[inline] fn default() -> Self { Self::new() }
+}
+
+impl<K:IpPrefix,V> RTrieMultiMap<K,V>
+{
+    /// Returns the number of prefixes of the multimap.
+    ///
+    /// Notice that it never equals zero since the top prefix is
+    /// always present in the multimap.
+    #[inline]
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> NonZeroUsize { self.0.len() }
+
+    /// Appends a value to the list associated to the prefix.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv4Addr;
+    /// let mut trie = Ipv4RTrieMultiMap::new();
+    /// let ip = "1.1.0.0/16".parse::<Ipv4Prefix>().unwrap();
+    ///
+    /// trie.insert(ip, Ipv4Addr::new(10,0,0,1));
+    /// trie.insert(ip, Ipv4Addr::new(10,0,0,2));
+    ///
+    /// assert_eq!( trie.get(&ip), Some([Ipv4Addr::new(10,0,0,1), Ipv4Addr::new(10,0,0,2)].as_slice()));
+    /// ```
+    pub fn insert(&mut self, k: K, v: V)
+    {
+        match self.0.get_mut(&k) {
+            Some(values) => values.push(v),
+            None => { self.0.insert(k, vec![v]); }
+        }
+    }
+
+    /// Gets the values associated with an exact match of the key.
+    #[inline]
+    pub fn get<Q>(&self, k: &Q) -> Option<&[V]>
+        where
+            Q: IpPrefix<Addr=K::Addr>,
+            K: IpPrefixCovering<Q>
+    {
+        self.0.get(k).map(Vec::as_slice)
+    }
+
+    /// Removes a prefix (exact match) and returns all its values.
+    /// # Panic
+    /// Panics if trying to remove the root prefix.
+    #[inline]
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<Vec<V>>
+        where
+            Q: IpPrefix<Addr=K::Addr>,
+            K: IpPrefixCovering<Q>
+    {
+        self.0.remove(k)
+    }
+
+    /// Gets the values associated with the longest prefix match of the key.
+    ///
+    /// As the top prefix always matches, it never fails
+    /// (but the list of values of the root prefix is usually empty).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv4Addr;
+    /// let mut trie = Ipv4RTrieMultiMap::new();
+    /// let ip = "1.1.0.0/16".parse::<Ipv4Prefix>().unwrap();
+    ///
+    /// trie.insert(ip, 1);
+    /// trie.insert(ip, 2);
+    ///
+    /// assert_eq!( trie.lookup(&Ipv4Addr::new(1,1,1,1)), (&ip, [1,2].as_slice()));
+    /// assert!( trie.lookup(&Ipv4Addr::new(2,1,1,1)).1.is_empty());
+    /// ```
+    #[inline]
+    pub fn lookup<Q>(&self, k: &Q) -> (&K, &[V])
+        where
+            Q: IpPrefix<Addr=K::Addr>,
+            K: IpPrefixCovering<Q>
+    {
+        let (k, v) = self.0.lookup(k);
+        (k, v.as_slice())
+    }
+
+    /// Iterates over all the prefixes with their values.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item=(&K,&[V])> + '_ {
+        self.0.iter().map(|(k,v)| (k, v.as_slice()))
+    }
+}
+
+impl<K:IpPrefix,V> Extend<(K, V)> for RTrieMultiMap<K,V>
+{
+    fn extend<I: IntoIterator<Item=(K,V)>>(&mut self, iter: I)
+    {
+        iter.into_iter().for_each(|(k,v)| self.insert(k,v))
+    }
+}
+
+impl<K:IpRootPrefix,V> FromIterator<(K, V)> for RTrieMultiMap<K,V>
+{
+    fn from_iter<I:IntoIterator<Item=(K,V)>>(iter: I) -> Self
+    {
+        let mut trie = Self::default();
+        trie.extend(iter);
+        trie
+    }
+}
@@ -1,6 +1,6 @@
-use std::cmp::Ordering;
+use core::cmp::Ordering;
 use ipnet::{Ipv4Net,Ipv6Net};
-use std::net::{Ipv4Addr,Ipv6Addr};
+use core::net::{Ipv4Addr,Ipv6Addr};
 use crate::*;
 
 #[doc(hidden)]
@@ -106,6 +106,8 @@ ipcover!(Ipv6Prefix, Ipv6Addr);
 ipcover!(Ipv6Net, Ipv6Prefix);
 ipcover!(Ipv6Net, Ipv6Addr);
 
+ipcover!(MacPrefix, MacAddr);
+
 
 
 
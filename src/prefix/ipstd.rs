@@ -10,6 +10,8 @@
 //! * [`Ipv4Net`] with a small extra cost to deal with non null trailing bits
 //! * [`Ipv6Net`] with a small extra cost to deal with non null trailing bits
 
+use alloc::vec::Vec;
+use ipnet::{Ipv4Subnets, Ipv6Subnets};
 use super::*;
 
 /// An Ipv4 prefix similar to [`Ipv4Net`] but with trailing bits
@@ -49,6 +51,84 @@ impl Ipv4Prefix {
             Ok( Self { addr: u32::from(addr) & u32::bitmask(len), len })
         }
     }
+
+    /// Shortens the prefix by the specified number of bits.
+    ///
+    /// This is similar to walking up the prefix hierarchy by `n` levels.
+    /// Returns `None` if `n` is greater than the length of the prefix
+    /// (i.e. if walking up past the root prefix).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let p = "10.0.0.0/8".parse::<Ipv4Prefix>().unwrap();
+    ///
+    /// assert_eq!( p.checked_shl(9), None);
+    /// assert_eq!( p.checked_shl(8), Some(Ipv4Prefix::root()));
+    /// assert_eq!( p.checked_shl(0), Some(p));
+    /// ```
+    #[inline]
+    pub fn checked_shl(self, n: u8) -> Option<Self>
+    {
+        self.len.checked_sub(n).map(|len| Self { addr: self.addr & u32::bitmask(len), len })
+    }
+
+    /// Gets the canonical prefix of a network.
+    ///
+    /// The host bits of the network are always cleared
+    /// (this is the same as the `From<Ipv4Net>` conversion).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let p = Ipv4Prefix::canonicalize("1.2.3.4/24".parse().unwrap());
+    /// assert_eq!( p.to_string(), "1.2.3.0/24");
+    /// ```
+    #[inline]
+    pub fn canonicalize(net: Ipv4Net) -> Self { Self::from(net) }
+
+    /// Gets the minimal list of prefixes which covers exactly a range of addresses.
+    ///
+    /// The range includes both `start` and `end` addresses and the prefixes
+    /// are sorted by address. The list is empty if `start` is greater than `end`.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv4Addr;
+    /// let prefixes = Ipv4Prefix::prefixes_for_range(Ipv4Addr::new(1,0,0,0), Ipv4Addr::new(1,0,0,255));
+    /// assert_eq!( prefixes, ["1.0.0.0/24".parse().unwrap()]);
+    ///
+    /// let prefixes = Ipv4Prefix::prefixes_for_range(Ipv4Addr::new(10,0,0,1), Ipv4Addr::new(10,0,0,6));
+    /// assert_eq!( prefixes.iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+    ///     ["10.0.0.1/32", "10.0.0.2/31", "10.0.0.4/31", "10.0.0.6/32"]);
+    /// ```
+    pub fn prefixes_for_range(start: Ipv4Addr, end: Ipv4Addr) -> Vec<Self>
+    {
+        Ipv4Subnets::new(start, end, 0).map(Self::from).collect()
+    }
+
+    /// Parses a prefix in CIDR notation or a bare address.
+    ///
+    /// A bare address is considered as a host prefix (i.e. a prefix of `/32`).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let p = Ipv4Prefix::parse_host_or_prefix("10.0.0.1").unwrap();
+    /// assert_eq!( p.to_string(), "10.0.0.1/32");
+    ///
+    /// let p = Ipv4Prefix::parse_host_or_prefix("10.0.0.0/24").unwrap();
+    /// assert_eq!( p.to_string(), "10.0.0.0/24");
+    /// ```
+    pub fn parse_host_or_prefix(s: &str) -> Result<Self, IpPrefixError>
+    {
+        if s.contains('/') {
+            s.parse()
+        } else {
+            Ok(Self::from(s.parse::<Ipv4Addr>()?))
+        }
+    }
 }
 
 /// An Ipv4 prefix similar to [`Ipv6Net`] but with trailing bits
@@ -89,6 +169,128 @@ impl Ipv6Prefix {
             Ok( Self { addr: u128::from(addr) & u128::bitmask(len), len })
         }
     }
+
+    /// Shortens the prefix by the specified number of bits.
+    ///
+    /// This is similar to walking up the prefix hierarchy by `n` levels.
+    /// Returns `None` if `n` is greater than the length of the prefix
+    /// (i.e. if walking up past the root prefix).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let p = "2001::/16".parse::<Ipv6Prefix>().unwrap();
+    ///
+    /// assert_eq!( p.checked_shl(17), None);
+    /// assert_eq!( p.checked_shl(16), Some(Ipv6Prefix::root()));
+    /// assert_eq!( p.checked_shl(0), Some(p));
+    /// ```
+    #[inline]
+    pub fn checked_shl(self, n: u8) -> Option<Self>
+    {
+        self.len.checked_sub(n).map(|len| Self { addr: self.addr & u128::bitmask(len), len })
+    }
+
+    /// Gets the canonical prefix of a network.
+    ///
+    /// The host bits of the network are always cleared
+    /// (this is the same as the `From<Ipv6Net>` conversion).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let p = Ipv6Prefix::canonicalize("2001:db8::1/32".parse().unwrap());
+    /// assert_eq!( p.to_string(), "2001:db8::/32");
+    /// ```
+    #[inline]
+    pub fn canonicalize(net: Ipv6Net) -> Self { Self::from(net) }
+
+    /// Gets the minimal list of prefixes which covers exactly a range of addresses.
+    ///
+    /// The range includes both `start` and `end` addresses and the prefixes
+    /// are sorted by address. The list is empty if `start` is greater than `end`.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let prefixes = Ipv6Prefix::prefixes_for_range("2001:db8::".parse().unwrap(), "2001:db8::ffff".parse().unwrap());
+    /// assert_eq!( prefixes, ["2001:db8::/112".parse::<Ipv6Prefix>().unwrap()]);
+    /// ```
+    pub fn prefixes_for_range(start: Ipv6Addr, end: Ipv6Addr) -> Vec<Self>
+    {
+        Ipv6Subnets::new(start, end, 0).map(Self::from).collect()
+    }
+
+    /// Parses a prefix in CIDR notation or a bare address.
+    ///
+    /// A bare address is considered as a host prefix (i.e. a prefix of `/128`).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let p = Ipv6Prefix::parse_host_or_prefix("2001:db8::1").unwrap();
+    /// assert_eq!( p.to_string(), "2001:db8::1/128");
+    ///
+    /// let p = Ipv6Prefix::parse_host_or_prefix("2001:db8::/32").unwrap();
+    /// assert_eq!( p.to_string(), "2001:db8::/32");
+    /// ```
+    pub fn parse_host_or_prefix(s: &str) -> Result<Self, IpPrefixError>
+    {
+        if s.contains('/') {
+            s.parse()
+        } else {
+            Ok(Self::from(s.parse::<Ipv6Addr>()?))
+        }
+    }
+
+    /// Parses a prefix as [`Self::parse_host_or_prefix`] but ignores the zone, if any.
+    ///
+    /// A zone (or scope id) follows the address as `%eth0` and is meaningless
+    /// for a prefix: it is found, for instance, in the dumps of `ip route`.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let p = Ipv6Prefix::parse_lenient("fe80::1%eth0/64").unwrap();
+    /// assert_eq!( p.to_string(), "fe80::/64");
+    ///
+    /// let p = Ipv6Prefix::parse_lenient("fe80::1%3").unwrap();
+    /// assert_eq!( p.to_string(), "fe80::1/128");
+    /// ```
+    pub fn parse_lenient(s: &str) -> Result<Self, IpPrefixError>
+    {
+        match s.split_once('%') {
+            None => Self::parse_host_or_prefix(s),
+            Some((addr, zone)) => {
+                let (zone, len) = zone.split_once('/').map_or((zone, None), |(z, l)| (z, Some(l)));
+                if zone.is_empty() {
+                    return Err(IpPrefixError::AddrParseError);
+                }
+                let addr = addr.parse::<Ipv6Addr>()?;
+                match len {
+                    None => Ok(Self::from(addr)),
+                    Some(len) => Self::new(addr, len.parse().map_err(|_| IpPrefixError::PrefixLenError)?),
+                }
+            }
+        }
+    }
+}
+
+/// Parses an IPv6 prefix, ignoring the zone of the address, if any.
+///
+/// This is the same as [`Ipv6Prefix::parse_lenient`], for the inputs
+/// such as the dumps of `ip route`.
+///
+/// # Example
+/// ```
+/// # use iptrie::*;
+/// let p = parse_ipv6_prefix_lenient("fe80::1%eth0/64").unwrap();
+/// assert_eq!( p.to_string(), "fe80::/64");
+/// ```
+#[inline]
+pub fn parse_ipv6_prefix_lenient(s: &str) -> Result<Ipv6Prefix, IpPrefixError>
+{
+    Ipv6Prefix::parse_lenient(s)
 }
 
 
@@ -114,6 +316,14 @@ macro_rules! ipprefix {
             #[inline] fn root() -> Self { Self { addr: 0, len: 0 } }
         }
 
+        impl IpPrefixFromSlot for $prefix
+        {
+            #[inline] fn from_slot(slot: Self::Slot, len: u8) -> Self {
+                assert!( len <= Self::MAX_LEN );
+                Self { addr: slot & <$slot>::bitmask(len), len }
+            }
+        }
+
         impl From<$ipnet> for $prefix
         {
             #[inline] fn from(value: $ipnet) -> Self {
@@ -126,6 +336,10 @@ macro_rules! ipprefix {
                 <$ipnet>::new(value.addr.into(), value.len()).unwrap()
             }
         }
+        impl From<$prefix> for IpNet
+        {
+            #[inline] fn from(value: $prefix) -> Self { IpNet::from(<$ipnet>::from(value)) }
+        }
         impl Display for $prefix {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
                 let ip = (*self).into();
@@ -168,6 +382,13 @@ macro_rules! ipprefix {
             #[inline] fn root() -> Self { Self::default() }
         }
 
+        impl IpPrefixFromSlot for $ipnet
+        {
+            #[inline] fn from_slot(slot: Self::Slot, len: u8) -> Self {
+                <$ipnet>::new((slot & <$slot>::bitmask(len)).into(), len).unwrap()
+            }
+        }
+
         impl IpPrefix for $ipaddr
         {
             type Slot = $slot;
@@ -0,0 +1,189 @@
+use core::fmt::{self, Debug, Display, Formatter};
+use core::str::FromStr;
+use crate::*;
+
+/// A MAC address (EUI-48)
+///
+/// It could be used as a prefix of length 48 to perform
+/// a longest prefix match on a [`MacPrefix`] trie.
+///
+/// # Example
+/// ```
+/// # use iptrie::*;
+/// let mac = "00:1a:2b:3c:4d:5e".parse::<MacAddr>().unwrap();
+/// assert_eq!( mac, MacAddr::new([0x00,0x1a,0x2b,0x3c,0x4d,0x5e]));
+/// assert_eq!( mac.to_string(), "00:1a:2b:3c:4d:5e");
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub struct MacAddr([u8; 6]);
+
+impl MacAddr {
+    /// Builds a MAC address from its six octets (in transmission order).
+    #[inline]
+    pub const fn new(octets: [u8; 6]) -> Self { Self(octets) }
+
+    /// Returns the six octets of this MAC address.
+    #[inline]
+    pub const fn octets(&self) -> [u8; 6] { self.0 }
+
+    // the 48 bits of the address, as the highest bits of the slot
+    #[inline]
+    const fn to_slot(self) -> u64 {
+        let o = self.0;
+        u64::from_be_bytes([o[0], o[1], o[2], o[3], o[4], o[5], 0, 0])
+    }
+
+    #[inline]
+    const fn from_slot(slot: u64) -> Self {
+        let b = slot.to_be_bytes();
+        Self([b[0], b[1], b[2], b[3], b[4], b[5]])
+    }
+}
+
+impl From<[u8; 6]> for MacAddr {
+    #[inline]
+    fn from(octets: [u8; 6]) -> Self { Self(octets) }
+}
+
+impl Display for MacAddr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let o = self.0;
+        write!(f, "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}", o[0], o[1], o[2], o[3], o[4], o[5])
+    }
+}
+
+impl Debug for MacAddr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { <MacAddr as Display>::fmt(self, f) }
+}
+
+impl FromStr for MacAddr {
+    type Err = IpPrefixError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut octets = [0u8; 6];
+        let mut parts = s.split(':');
+        for o in octets.iter_mut() {
+            *o = parts.next()
+                .filter(|p| p.len() == 2)
+                .and_then(|p| u8::from_str_radix(p, 16).ok())
+                .ok_or(IpPrefixError::AddrParseError)?;
+        }
+        if parts.next().is_some() {
+            Err(IpPrefixError::AddrParseError)
+        } else {
+            Ok(Self(octets))
+        }
+    }
+}
+
+impl IpPrefix for MacAddr
+{
+    type Slot = u64;
+    #[inline] fn bitslot(&self) -> Self::Slot { self.to_slot() }
+    #[inline] fn bitslot_trunc(&self) -> Self::Slot { self.to_slot() }
+    #[inline] fn len(&self) -> u8 { 48 }
+
+    const MAX_LEN: u8 = 48;
+    type Addr = MacAddr;
+    #[inline] fn network(&self) -> Self::Addr { *self }
+}
+
+impl IpPrivatePrefix for MacAddr
+{
+    /// Checks if the address is locally administered.
+    #[inline]
+    fn is_private(&self) -> bool { self.0[0] & 0x02 != 0 }
+}
+
+
+/// A MAC address prefix (as an OUI)
+///
+/// This prefix is not an Ip one but it shows that the tries work
+/// on any fixed-width keys: the 48 bits of the address are stored
+/// as the highest bits of a `u64` slot.
+///
+/// # Example
+/// ```
+/// # use iptrie::*;
+/// let trie = RTrieMap::from_iter([
+///     ("00:1a:2b:00:00:00/24".parse::<MacPrefix>().unwrap(), "vendor"),
+///     ("00:1a:2b:3c:00:00/32".parse::<MacPrefix>().unwrap(), "product") ]);
+///
+/// let mac = "00:1a:2b:3c:4d:5e".parse::<MacAddr>().unwrap();
+/// assert_eq!( trie.lookup(&mac), (&"00:1a:2b:3c:00:00/32".parse().unwrap(), &"product"));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub struct MacPrefix {
+    slot: u64,
+    len: u8
+}
+
+impl MacPrefix {
+    pub fn new(addr: MacAddr, len: u8) -> Result<Self, IpPrefixError>
+    {
+        if len > Self::MAX_LEN {
+            Err(IpPrefixError::PrefixLenError)
+        } else {
+            Ok(Self { slot: addr.to_slot() & u64::bitmask(len), len })
+        }
+    }
+}
+
+impl IpPrefix for MacPrefix
+{
+    type Slot = u64;
+    #[inline] fn bitslot(&self) -> Self::Slot { self.slot }
+    #[inline] fn bitslot_trunc(&self) -> Self::Slot { self.slot }
+    #[inline] fn len(&self) -> u8 { self.len }
+
+    const MAX_LEN: u8 = 48;
+    type Addr = MacAddr;
+    #[inline] fn network(&self) -> Self::Addr { MacAddr::from_slot(self.slot) }
+}
+
+impl IpRootPrefix for MacPrefix
+{
+    #[inline] fn root() -> Self { Self { slot: 0, len: 0 } }
+}
+
+impl IpPrefixFromSlot for MacPrefix
+{
+    #[inline] fn from_slot(slot: Self::Slot, len: u8) -> Self {
+        assert!( len <= Self::MAX_LEN );
+        Self { slot: slot & u64::bitmask(len), len }
+    }
+}
+
+impl IpPrivatePrefix for MacPrefix
+{
+    /// Checks if all the addresses are locally administered.
+    #[inline]
+    fn is_private(&self) -> bool { self.len >= 7 && self.slot.is_set(7) }
+}
+
+impl From<MacAddr> for MacPrefix
+{
+    #[inline] fn from(addr: MacAddr) -> Self { Self { slot: addr.to_slot(), len: 48 } }
+}
+
+impl Display for MacPrefix {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network(), self.len)
+    }
+}
+
+impl Debug for MacPrefix {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { <MacPrefix as Display>::fmt(self, f) }
+}
+
+impl FromStr for MacPrefix {
+    type Err = IpPrefixError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('/') {
+            None => Ok(Self::from(s.parse::<MacAddr>()?)),
+            Some((addr, len)) => {
+                let len = len.parse::<u8>().map_err(|_| IpPrefixError::PrefixLenError)?;
+                Self::new(addr.parse()?, len)
+            }
+        }
+    }
+}
@@ -7,31 +7,90 @@ mod cover;
 
 #[cfg(test)] mod tests;
 mod private;
+mod special;
 mod shorten;
 mod network;
+mod mac;
 
-use std::error::Error;
 pub use slot::*;
 pub use ipstd::*;
 pub use cover::*;
 pub use shorten::*;
 pub use network::Ipv6NetPrefix;
+pub use mac::{MacAddr, MacPrefix};
 
-use std::fmt;
-use std::fmt::{Debug, Display};
-use std::hash::Hash;
-use std::net::{Ipv4Addr, Ipv6Addr};
-use std::str::FromStr;
+use core::fmt;
+use core::fmt::{Debug, Display};
+use core::hash::Hash;
+use core::net::{Ipv4Addr, Ipv6Addr};
+use core::str::FromStr;
 
-use ipnet::{Ipv4Net, Ipv6Net};
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
 pub use crate::prefix::private::IpPrivatePrefix;
+pub use crate::prefix::special::IpSpecialPurpose;
 
 pub trait IpRootPrefix: IpPrefix {
     /// Root prefix has a length of 0
     fn root() -> Self; // root prefix, of len =0
 }
 
+/// Ip prefix which can be built from a slot of bits
+pub trait IpPrefixFromSlot: IpPrefix {
+    /// Builds a prefix from a slot and a length.
+    ///
+    /// All the bits greater than the prefix length are set to `0`.
+    ///
+    /// # Panics
+    /// Panics if the length is greater than the maximum allowed.
+    fn from_slot(slot: Self::Slot, len: u8) -> Self;
+
+    /// Splits this prefix in its two halves (one bit longer).
+    ///
+    /// Returns `None` if this prefix has already the maximum length.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let p = "1.1.0.0/16".parse::<Ipv4Prefix>().unwrap();
+    /// let (a,b) = p.split().unwrap();
+    /// assert_eq!( a.to_string(), "1.1.0.0/17");
+    /// assert_eq!( b.to_string(), "1.1.128.0/17");
+    /// ```
+    fn split(&self) -> Option<(Self, Self)>
+    {
+        if self.len() >= Self::MAX_LEN {
+            None
+        } else {
+            let len = self.len() + 1;
+            let slot = self.bitslot_trunc();
+            Some((Self::from_slot(slot, len), Self::from_slot(slot | Self::Slot::single_bit(len), len)))
+        }
+    }
+}
+
 /// Ip prefix (as bit prefix)
+///
+/// # Why `Copy`
+/// A prefix is expected to be a small value (a slot of bits and a length)
+/// and the tries rely on it:
+/// * when inserting, the prefix of the new leaf is copied since it is read
+///   while the branching nodes are modified,
+/// * when mapping the values or rebuilding a trie, the prefixes are duplicated,
+/// * many set operations return owned prefixes (as [`crate::RTrieSet::bounding_prefix`]
+///   or [`crate::RTrieSet::explode_to`]) built from the stored ones.
+///
+/// So any extra data (as a label) should not be carried by the prefix
+/// but stored as the value of a map.
+///
+/// # Example
+/// ```
+/// # use iptrie::*;
+/// use std::net::Ipv4Addr;
+/// let mut labels = Ipv4RTrieMap::with_root(String::from("default"));
+/// labels.insert("10.0.0.0/8".parse().unwrap(), String::from("private"));
+///
+/// assert_eq!( labels.lookup(&Ipv4Addr::new(10,1,1,1)).1, "private");
+/// ```
 #[allow(clippy::len_without_is_empty)]
 pub trait IpPrefix: IpPrivatePrefix+Debug+Clone+Copy
 {
@@ -66,6 +125,40 @@ pub trait IpPrefix: IpPrivatePrefix+Debug+Clone+Copy
     #[inline]
     fn bitmask(&self) -> Self::Slot { <Self::Slot as BitSlot>::bitmask(self.len()) }
 
+    /// Checks if the given bits are covered by this prefix.
+    ///
+    /// This is a faster way to check if an address is covered by this prefix,
+    /// without any conversion, since the bits are those of the slot
+    /// (for instance, a `u32` for an Ipv4 address).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let p = "1.1.0.0/16".parse::<Ipv4Prefix>().unwrap();
+    /// assert!( p.contains_bits(0x01010203) );
+    /// assert!( !p.contains_bits(0x01020304) );
+    /// ```
+    #[inline]
+    fn contains_bits(&self, bits: Self::Slot) -> bool { bits & self.bitmask() == self.bitslot_trunc() }
+
+    /// Checks if all the bits beyond the prefix length are set to 0.
+    ///
+    /// This is always true for the prefixes which mask their bits at construction
+    /// (as [`Ipv4Prefix`] or [`Ipv6Prefix`]) but not necessarily for [`Ipv4Net`]
+    /// or [`Ipv6Net`]. It is useful to check the keys before an exact match.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use ipnet::Ipv4Net;
+    /// let net = "1.2.3.4/24".parse::<Ipv4Net>().unwrap();
+    /// assert!( !net.is_canonical() );
+    /// assert!( net.trunc().is_canonical() );
+    /// assert!( Ipv4Prefix::from(net).is_canonical() );
+    /// ```
+    #[inline]
+    fn is_canonical(&self) -> bool { self.bitslot() == self.bitslot_trunc() }
+
     /// The maximum allowed length for this prefix
     const MAX_LEN: u8;
 
@@ -76,6 +169,30 @@ pub trait IpPrefix: IpPrivatePrefix+Debug+Clone+Copy
     ///
     /// All the bits greater than the prefix length are set to `0`
     fn network(&self) -> Self::Addr;
+
+    /// Checks if the two prefixes define the same network.
+    ///
+    /// Both prefixes should have the same length and the same
+    /// network address (the trailing bits are ignored) but could be
+    /// of different types.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use ipnet::Ipv6Net;
+    /// let a = "1:2:3::/48".parse::<Ipv6Prefix>().unwrap();
+    /// let b = "1:2:3::1/48".parse::<Ipv6Net>().unwrap();
+    /// let c = "1:2:3::/48".parse::<Ipv6NetPrefix>().unwrap();
+    ///
+    /// assert!( a.same_network(&b) );
+    /// assert!( b.same_network(&c) );
+    /// assert!( !a.same_network(&"1:2:3::/56".parse::<Ipv6Prefix>().unwrap()) );
+    /// ```
+    #[inline]
+    fn same_network<Q: IpPrefix<Addr=Self::Addr>>(&self, other: &Q) -> bool
+    {
+        self.len() == other.len() && self.network() == other.network()
+    }
 }
 
 
@@ -100,6 +217,12 @@ pub enum IpPrefixError {
     /// It occurs also if when parsing an Ipv4 (resp. Ipv6) address on a string
     /// which contains an Ipv6 (resp. Ipv4) syntax.
     AddrParseError,
+
+    /// The address has some bits set beyond the prefix length.
+    ///
+    /// It occurs only with the conversions which refuse to lose
+    /// the host bits (as [`Ipv6NetPrefix::try_from_exact`]).
+    HostBitsError,
 }
 
 impl Display for IpPrefixError
@@ -112,11 +235,15 @@ impl Display for IpPrefixError
             IpPrefixError::AddrParseError => {
                 fmt.write_str("invalid IP address syntax")
             }
+            IpPrefixError::HostBitsError => {
+                fmt.write_str("IP address with host bits set")
+            }
         }
     }
 }
 
-impl Error for IpPrefixError {}
+#[cfg(feature = "std")]
+impl std::error::Error for IpPrefixError {}
 
 impl From<ipnet::AddrParseError> for IpPrefixError {
     fn from(_: ipnet::AddrParseError) -> Self {
@@ -124,8 +251,8 @@ impl From<ipnet::AddrParseError> for IpPrefixError {
     }
 }
 
-impl From<std::net::AddrParseError> for IpPrefixError {
-    fn from(_: std::net::AddrParseError) -> Self {
+impl From<core::net::AddrParseError> for IpPrefixError {
+    fn from(_: core::net::AddrParseError) -> Self {
         IpPrefixError::AddrParseError
     }
 }
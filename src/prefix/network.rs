@@ -1,8 +1,8 @@
-use std::fmt::{Debug, Display, Formatter};
-use std::net::Ipv6Addr;
-use std::str::FromStr;
+use core::fmt::{Debug, Display, Formatter};
+use core::net::Ipv6Addr;
+use core::str::FromStr;
 use ipnet::{IpNet, Ipv6Net, PrefixLenError};
-use crate::{BitSlot, IpPrefix, IpPrefixError, IpPrefixShortening, IpPrivatePrefix, IpRootPrefix, Ipv6Prefix};
+use crate::{BitSlot, IpPrefix, IpPrefixError, IpPrefixFromSlot, IpPrefixShortening, IpPrivatePrefix, IpRootPrefix, Ipv6Prefix};
 
 /// An Ipv6 prefix limited to 64 bits (EXPERIMENTAL)
 ///
@@ -40,6 +40,30 @@ impl Ipv6NetPrefix {
         Self { slot: ((ip.to_bits() >> 64) as u64) & bitmask, len }
     }
 
+    /// Converts an Ipv6 network without losing any bit.
+    ///
+    /// Unlike `TryFrom<Ipv6Net>` which silently masks the host bits,
+    /// an error is returned if the address has some bits set beyond
+    /// the prefix length.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use ipnet::Ipv6Net;
+    /// let clean = "2001:db8:1::/48".parse::<Ipv6Net>().unwrap();
+    /// let lossy = "2001:db8:1::1/48".parse::<Ipv6Net>().unwrap();
+    ///
+    /// assert_eq!( Ipv6NetPrefix::try_from_exact(clean).map(|p| p.to_string()), Ok("2001:db8:1::/48".to_string()));
+    /// assert_eq!( Ipv6NetPrefix::try_from_exact(lossy), Err(IpPrefixError::HostBitsError));
+    /// ```
+    pub fn try_from_exact(net: Ipv6Net) -> Result<Self, IpPrefixError>
+    {
+        if net.addr() != net.network() {
+            Err(IpPrefixError::HostBitsError)
+        } else {
+            Ok(Self::new(net.addr(), net.prefix_len())?)
+        }
+    }
 }
 
 impl IpPrefix for Ipv6NetPrefix {
@@ -77,13 +101,13 @@ impl IpPrivatePrefix for Ipv6NetPrefix {
 }
 
 impl Debug for Ipv6NetPrefix {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         <Ipv6NetPrefix as Display>::fmt(self, f)
     }
 }
 
 impl Display for Ipv6NetPrefix {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         <Ipv6Net as Display>::fmt(&(*self).into(), f)
     }
 }
@@ -95,6 +119,15 @@ impl IpRootPrefix for Ipv6NetPrefix
     }
 }
 
+impl IpPrefixFromSlot for Ipv6NetPrefix
+{
+    #[inline]
+    fn from_slot(slot: Self::Slot, len: u8) -> Self {
+        assert!(len <= 64);
+        Self { slot: slot & u64::bitmask(len), len }
+    }
+}
+
 
 impl IpPrefixShortening for Ipv6NetPrefix
 {
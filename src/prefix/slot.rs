@@ -1,7 +1,7 @@
 
-use std::ops::{Shr, Shl, BitAnd, Not, BitOr, BitXor};
-use std::fmt::{Binary, Debug};
-use std::hash::Hash;
+use core::ops::{Shr, Shl, BitAnd, Not, BitOr, BitXor};
+use core::fmt::{Binary, Debug};
+use core::hash::Hash;
 
 /// A fixed-length slot of bits.
 ///
@@ -87,7 +87,7 @@ pub trait BitSlot :
 macro_rules! bitslot {
     ($slot:ty) => {
         impl BitSlot for $slot {
-            const LEN: u8 = std::mem::size_of::<$slot>() as u8 * 8;
+            const LEN: u8 = core::mem::size_of::<$slot>() as u8 * 8;
             fn first_bit(&self) -> u8 {
                 self.leading_zeros() as u8 + 1
             }
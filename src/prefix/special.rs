@@ -0,0 +1,148 @@
+use core::net::{Ipv4Addr, Ipv6Addr};
+use ipnet::{Ipv4Net, Ipv6Net};
+use crate::*;
+
+/// Classifies the range of IP addresses according to the special-purpose ranges.
+///
+/// The ranges are those of the IANA special-purpose address registries.
+/// A prefix is classified in a range only if it is fully within it
+/// (as for [`IpPrivatePrefix::is_private`]):
+///
+/// | Method | IPv4 | IPv6 |
+/// |---------|---------|---------|
+/// | `is_multicast` | 224.0.0.0/4 | ff00::/8 |
+/// | `is_loopback` | 127.0.0.0/8 | ::1/128 |
+/// | `is_documentation` | 192.0.2.0/24, 198.51.100.0/24, 203.0.113.0/24 | 2001:db8::/32, 3fff::/20 |
+///
+/// A prefix is global if it does not intersect any range marked
+/// as not globally reachable by the IANA registries.
+///
+/// # Example
+/// ```
+/// # use iptrie::*;
+/// assert!( "224.0.0.0/4".parse::<Ipv4Prefix>().unwrap().is_multicast() );
+/// assert!( !"224.0.0.0/3".parse::<Ipv4Prefix>().unwrap().is_multicast() );
+/// assert!( "1.1.1.0/24".parse::<Ipv4Prefix>().unwrap().is_global() );
+/// assert!( !"10.1.0.0/16".parse::<Ipv4Prefix>().unwrap().is_global() );
+/// assert!( !"0.0.0.0/0".parse::<Ipv4Prefix>().unwrap().is_global() );
+/// ```
+pub trait IpSpecialPurpose {
+    fn is_multicast(&self) -> bool;
+    fn is_loopback(&self) -> bool;
+    fn is_documentation(&self) -> bool;
+    fn is_global(&self) -> bool;
+}
+
+const V4_MULTICAST: [(Ipv4Addr,u8); 1] = [(Ipv4Addr::new(224,0,0,0), 4)];
+const V4_LOOPBACK: [(Ipv4Addr,u8); 1] = [(Ipv4Addr::new(127,0,0,0), 8)];
+const V4_DOCUMENTATION: [(Ipv4Addr,u8); 3] = [
+    (Ipv4Addr::new(192,0,2,0), 24),
+    (Ipv4Addr::new(198,51,100,0), 24),
+    (Ipv4Addr::new(203,0,113,0), 24),
+];
+const V4_NOT_GLOBAL: [(Ipv4Addr,u8); 14] = [
+    (Ipv4Addr::new(0,0,0,0), 8), // this network
+    (Ipv4Addr::new(10,0,0,0), 8), // private use
+    (Ipv4Addr::new(100,64,0,0), 10), // shared address space
+    (Ipv4Addr::new(127,0,0,0), 8), // loopback
+    (Ipv4Addr::new(169,254,0,0), 16), // link local
+    (Ipv4Addr::new(172,16,0,0), 12), // private use
+    (Ipv4Addr::new(192,0,0,0), 24), // IETF protocol assignments
+    (Ipv4Addr::new(192,0,2,0), 24), // documentation
+    (Ipv4Addr::new(192,168,0,0), 16), // private use
+    (Ipv4Addr::new(198,18,0,0), 15), // benchmarking
+    (Ipv4Addr::new(198,51,100,0), 24), // documentation
+    (Ipv4Addr::new(203,0,113,0), 24), // documentation
+    (Ipv4Addr::new(240,0,0,0), 4), // reserved
+    (Ipv4Addr::new(255,255,255,255), 32), // limited broadcast
+];
+
+const V6_MULTICAST: [(Ipv6Addr,u8); 1] = [(Ipv6Addr::new(0xff00,0,0,0,0,0,0,0), 8)];
+const V6_LOOPBACK: [(Ipv6Addr,u8); 1] = [(Ipv6Addr::LOCALHOST, 128)];
+const V6_DOCUMENTATION: [(Ipv6Addr,u8); 2] = [
+    (Ipv6Addr::new(0x2001,0xdb8,0,0,0,0,0,0), 32),
+    (Ipv6Addr::new(0x3fff,0,0,0,0,0,0,0), 20),
+];
+const V6_NOT_GLOBAL: [(Ipv6Addr,u8); 11] = [
+    (Ipv6Addr::UNSPECIFIED, 128), // unspecified
+    (Ipv6Addr::LOCALHOST, 128), // loopback
+    (Ipv6Addr::new(0,0,0,0,0,0xffff,0,0), 96), // Ipv4-mapped
+    (Ipv6Addr::new(0x64,0xff9b,1,0,0,0,0,0), 48), // local-use Ipv4/Ipv6 translation
+    (Ipv6Addr::new(0x100,0,0,0,0,0,0,0), 64), // discard-only
+    (Ipv6Addr::new(0x2001,0,0,0,0,0,0,0), 23), // IETF protocol assignments
+    (Ipv6Addr::new(0x2001,0xdb8,0,0,0,0,0,0), 32), // documentation
+    (Ipv6Addr::new(0x3fff,0,0,0,0,0,0,0), 20), // documentation
+    (Ipv6Addr::new(0xfc00,0,0,0,0,0,0,0), 7), // unique-local
+    (Ipv6Addr::new(0xfe80,0,0,0,0,0,0,0), 10), // link-local
+    (Ipv6Addr::new(0xff00,0,0,0,0,0,0,0), 8), // multicast
+];
+
+macro_rules! special {
+    ($ipnet:ident, $within:ident, $multicast:ident, $loopback:ident, $documentation:ident, $notglobal:ident, $($prefix:ty),*) => {
+        $(
+        impl IpSpecialPurpose for $prefix
+        {
+            #[inline]
+            fn is_multicast(&self) -> bool { $within((*self).into(), &$multicast) }
+
+            #[inline]
+            fn is_loopback(&self) -> bool { $within((*self).into(), &$loopback) }
+
+            #[inline]
+            fn is_documentation(&self) -> bool { $within((*self).into(), &$documentation) }
+
+            #[inline]
+            fn is_global(&self) -> bool {
+                let net: $ipnet = (*self).into();
+                !$notglobal.iter()
+                    .map(|&(addr,len)| $ipnet::new(addr,len).unwrap())
+                    .any(|range| range.contains(&net) || net.contains(&range))
+            }
+        }
+        )*
+    }
+}
+
+#[inline]
+fn within_v4(net: Ipv4Net, ranges: &[(Ipv4Addr,u8)]) -> bool {
+    ranges.iter().any(|&(addr,len)| Ipv4Net::new(addr,len).unwrap().contains(&net))
+}
+
+#[inline]
+fn within_v6(net: Ipv6Net, ranges: &[(Ipv6Addr,u8)]) -> bool {
+    ranges.iter().any(|&(addr,len)| Ipv6Net::new(addr,len).unwrap().contains(&net))
+}
+
+special!(Ipv4Net, within_v4, V4_MULTICAST, V4_LOOPBACK, V4_DOCUMENTATION, V4_NOT_GLOBAL, Ipv4Prefix, Ipv4Net);
+special!(Ipv6Net, within_v6, V6_MULTICAST, V6_LOOPBACK, V6_DOCUMENTATION, V6_NOT_GLOBAL, Ipv6Prefix, Ipv6Net, Ipv6NetPrefix);
+
+#[cfg(test)] mod tests {
+    use std::str::FromStr;
+    use ipnet::*;
+    use crate::*;
+
+    #[test]
+    fn special_ipv4() {
+        assert!(Ipv4Prefix::from_str("224.0.0.0/4").unwrap().is_multicast());
+        assert!(!Ipv4Prefix::from_str("224.0.0.0/3").unwrap().is_multicast());
+        assert!(Ipv4Net::from_str("127.0.0.1/32").unwrap().is_loopback());
+        assert!(Ipv4Net::from_str("198.51.100.128/25").unwrap().is_documentation());
+        assert!(!Ipv4Net::from_str("198.51.0.0/16").unwrap().is_documentation());
+        assert!(Ipv4Prefix::from_str("8.8.8.0/24").unwrap().is_global());
+        assert!(!Ipv4Prefix::from_str("8.0.0.0/4").unwrap().is_global());
+        assert!(!Ipv4Prefix::from_str("192.168.1.0/24").unwrap().is_global());
+    }
+
+    #[test]
+    fn special_ipv6()
+    {
+        assert!(Ipv6Prefix::from_str("ff02::/16").unwrap().is_multicast());
+        assert!(!Ipv6Prefix::from_str("fe00::/7").unwrap().is_multicast());
+        assert!(Ipv6Net::from_str("::1/128").unwrap().is_loopback());
+        assert!(!Ipv6NetPrefix::from_str("::/64").unwrap().is_loopback());
+        assert!(Ipv6NetPrefix::from_str("2001:db8:1::/48").unwrap().is_documentation());
+        assert!(Ipv6Prefix::from_str("2a00::/16").unwrap().is_global());
+        assert!(!Ipv6Prefix::from_str("2000::/3").unwrap().is_global());
+        assert!(!Ipv6NetPrefix::from_str("fd00::/8").unwrap().is_global());
+    }
+}
@@ -29,6 +29,16 @@ fn parse_errors() {
     assert_eq!("1.1.1.1/12".parse::<Ipv6NetPrefix>(), Err(IpPrefixError::AddrParseError));
 }
 
+#[test]
+fn parse_host_or_prefix() {
+    assert_eq!( Ipv4Prefix::parse_host_or_prefix("10.0.0.1"), "10.0.0.1/32".parse());
+    assert_eq!( Ipv4Prefix::parse_host_or_prefix("10.0.0.0/24"), "10.0.0.0/24".parse());
+    assert!( Ipv4Prefix::parse_host_or_prefix("10.0.0.0/33").is_err());
+    assert_eq!( Ipv4Prefix::parse_host_or_prefix("1::1"), Err(IpPrefixError::AddrParseError));
+    assert_eq!( Ipv6Prefix::parse_host_or_prefix("1::1").map(|p| p.len()), Ok(128));
+    assert_eq!( Ipv6Prefix::parse_host_or_prefix("1::/16"), "1::/16".parse());
+}
+
 
 #[test]
 fn prefix_ipv4_trunc()
@@ -143,4 +153,143 @@ fn coverage_std_fn_for<P>()
     assert!(p == p.clone());
     assert_eq!(p.to_string(), p.clone().to_string());
     let _ = HashSet::<P>::from_iter(std::iter::once(p));
-}
\ No newline at end of file
+}
+#[test]
+fn prefix_same_network()
+{
+    let mut rng = thread_rng();
+    let len = Uniform::<u8>::from(0..=64);
+    let addr = Uniform::<u128>::from(0..=u128::MAX);
+
+    (0..10_000).for_each(|_| {
+        let addr = Ipv6Addr::from(addr.sample(&mut rng));
+        let l1 = len.sample(&mut rng);
+        let l2 = len.sample(&mut rng);
+
+        let a = Ipv6Prefix::new(addr, l1).unwrap();
+        let b = Ipv6Net::new(addr, l1).unwrap();
+        let c = Ipv6NetPrefix::new(addr, l1).unwrap();
+        assert!( a.same_network(&b) && b.same_network(&c) && c.same_network(&a) );
+
+        let d = Ipv6NetPrefix::new(addr, l2).unwrap();
+        assert_eq!( a.same_network(&d), l1 == l2 );
+        assert_eq!( b.same_network(&d), l1 == l2 );
+    })
+}
+
+#[test]
+fn prefix_contains_bits()
+{
+    let mut rng = thread_rng();
+    let len = Uniform::<u8>::from(0..=32);
+    let addr = Uniform::<u32>::from(0..=u32::MAX);
+
+    (0..10_000).for_each(|_| {
+        let p = Ipv4Prefix::new(addr.sample(&mut rng).into(), len.sample(&mut rng)).unwrap();
+        // addresses close to the prefix, to get matching ones
+        let bits = p.bitslot_trunc() ^ addr.sample(&mut rng).checked_shr(len.sample(&mut rng).into()).unwrap_or(0);
+        assert_eq!( p.contains_bits(bits), p.covers(&Ipv4Addr::from(bits)));
+    });
+}
+
+#[test]
+fn ipv6_net_prefix_exact_conversion()
+{
+    let net = |s: &str| s.parse::<Ipv6Net>().unwrap();
+
+    let clean = Ipv6NetPrefix::try_from_exact(net("2001:db8:1::/48")).unwrap();
+    assert_eq!( clean, Ipv6NetPrefix::try_from(net("2001:db8:1::/48")).unwrap());
+    assert_eq!( clean.to_string(), "2001:db8:1::/48");
+
+    // the same network but with host bits set
+    assert_eq!( Ipv6NetPrefix::try_from_exact(net("2001:db8:1:2::/48")), Err(IpPrefixError::HostBitsError));
+    assert_eq!( Ipv6NetPrefix::try_from(net("2001:db8:1:2::/48")), Ok(clean));
+    assert_eq!( Ipv6NetPrefix::try_from_exact(net("2001:db8:1::1/64")), Err(IpPrefixError::HostBitsError));
+
+    // too long to be stored
+    assert_eq!( Ipv6NetPrefix::try_from_exact(net("2001:db8:1::/80")), Err(IpPrefixError::PrefixLenError));
+}
+
+#[test]
+fn canonical_prefixes()
+{
+    let p = Ipv4Prefix::canonicalize("1.2.3.4/24".parse().unwrap());
+    assert_eq!( p.to_string(), "1.2.3.0/24");
+    assert_eq!( p, "1.2.3.0/24".parse::<Ipv4Prefix>().unwrap());
+
+    let p = Ipv6Prefix::canonicalize("2001:db8:1:2::3/48".parse().unwrap());
+    assert_eq!( p.to_string(), "2001:db8:1::/48");
+    assert_eq!( Ipv6Prefix::canonicalize("::1/0".parse().unwrap()), Ipv6Prefix::root());
+}
+
+#[test]
+fn range_to_prefixes()
+{
+    let v4 = |s: &str| s.parse::<Ipv4Addr>().unwrap();
+    let cidr = |prefixes: Vec<Ipv4Prefix>| prefixes.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+
+    assert_eq!( cidr(Ipv4Prefix::prefixes_for_range(v4("1.0.0.0"), v4("1.0.0.255"))), ["1.0.0.0/24"]);
+    assert_eq!( cidr(Ipv4Prefix::prefixes_for_range(v4("1.0.0.1"), v4("1.0.0.1"))), ["1.0.0.1/32"]);
+    assert_eq!( cidr(Ipv4Prefix::prefixes_for_range(v4("0.0.0.0"), v4("255.255.255.255"))), ["0.0.0.0/0"]);
+    assert_eq!( cidr(Ipv4Prefix::prefixes_for_range(v4("192.168.0.10"), v4("192.168.2.5"))),
+                ["192.168.0.10/31", "192.168.0.12/30", "192.168.0.16/28", "192.168.0.32/27", "192.168.0.64/26",
+                 "192.168.0.128/25", "192.168.1.0/24", "192.168.2.0/30", "192.168.2.4/31"]);
+    assert!( Ipv4Prefix::prefixes_for_range(v4("1.0.0.1"), v4("1.0.0.0")).is_empty());
+
+    // the prefixes cover exactly the range
+    let prefixes = Ipv4Prefix::prefixes_for_range(v4("10.0.0.3"), v4("10.0.1.200"));
+    let size = prefixes.iter().map(|p| 1u32 << (32 - p.len())).sum::<u32>();
+    assert_eq!( size, u32::from(v4("10.0.1.200")) - u32::from(v4("10.0.0.3")) + 1);
+
+    let v6 = |s: &str| s.parse::<Ipv6Addr>().unwrap();
+    let prefixes = Ipv6Prefix::prefixes_for_range(v6("2001:db8::1"), v6("2001:db8::ffff"));
+    assert_eq!( prefixes.len(), 16);
+    assert_eq!( prefixes[0].to_string(), "2001:db8::1/128");
+    assert_eq!( prefixes[15].to_string(), "2001:db8::8000/113");
+}
+
+#[test]
+fn canonical_forms()
+{
+    let net = "1.2.3.4/24".parse::<Ipv4Net>().unwrap();
+    assert!( !net.is_canonical());
+    assert!( Ipv4Prefix::from(net).is_canonical());
+    assert!( Ipv4Prefix::canonicalize(net).is_canonical());
+    assert!( "1.2.3.0/24".parse::<Ipv4Net>().unwrap().is_canonical());
+    assert!( "1.2.3.4/32".parse::<Ipv4Net>().unwrap().is_canonical());
+    assert!( Ipv4Addr::new(1,2,3,4).is_canonical());
+
+    let net = "2001:db8::1/64".parse::<Ipv6Net>().unwrap();
+    assert!( !net.is_canonical());
+    assert!( Ipv6Prefix::from(net).is_canonical());
+    assert!( Ipv6NetPrefix::try_from(net).unwrap().is_canonical());
+    assert!( "::/0".parse::<Ipv6Net>().unwrap().is_canonical());
+
+    // random prefixes are always canonical once masked
+    let mut rng = thread_rng();
+    (0..100).for_each(|_| {
+        let net = Ipv4Net::new(Ipv4Addr::from(rng.next_u32()), (rng.next_u32() % 33) as u8).unwrap();
+        assert_eq!( net.is_canonical(), net == net.trunc());
+        assert!( Ipv4Prefix::from(net).is_canonical());
+    });
+}
+
+#[test]
+fn lenient_ipv6_parsing()
+{
+    let p = |s: &str| s.parse::<Ipv6Prefix>().unwrap();
+
+    assert_eq!( Ipv6Prefix::parse_lenient("fe80::1%eth0/64"), Ok(p("fe80::/64")));
+    assert_eq!( Ipv6Prefix::parse_lenient("fe80::1%eth0"), Ok(p("fe80::1/128")));
+    assert_eq!( Ipv6Prefix::parse_lenient("fe80::%12/10"), Ok(p("fe80::/10")));
+    assert_eq!( Ipv6Prefix::parse_lenient("2001:db8::/32"), Ok(p("2001:db8::/32")));
+    assert_eq!( Ipv6Prefix::parse_lenient("2001:db8::1"), Ok(p("2001:db8::1/128")));
+    assert!( "fe80::1%eth0/64".parse::<Ipv6Prefix>().is_err());
+    assert_eq!( parse_ipv6_prefix_lenient("fe80::1%eth0/64"), Ok(p("fe80::/64")));
+
+    assert_eq!( Ipv6Prefix::parse_lenient("fe80::1%/64"), Err(IpPrefixError::AddrParseError));
+    assert_eq!( Ipv6Prefix::parse_lenient("fe80::g%eth0/64"), Err(IpPrefixError::AddrParseError));
+    assert_eq!( Ipv6Prefix::parse_lenient("1.1.1.1%eth0/24"), Err(IpPrefixError::AddrParseError));
+    assert_eq!( Ipv6Prefix::parse_lenient("fe80::1%eth0/129"), Err(IpPrefixError::PrefixLenError));
+    assert_eq!( Ipv6Prefix::parse_lenient("fe80::1%eth0/x"), Err(IpPrefixError::PrefixLenError));
+}
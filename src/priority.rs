@@ -0,0 +1,118 @@
+use core::num::NonZeroUsize;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::map::RTrieMap;
+
+use crate::prefix::*;
+
+/// A set of Ip prefixes with priorities based on a radix binary trie
+///
+/// This is suitable for ACLs where the highest priority rule wins
+/// whatever the length of its prefix: the lookup returns, among all the
+/// covering prefixes, the one with the highest priority (instead of the longest one).
+///
+/// Prefixes with the same range but different host bits (as for [`ipnet::Ipv4Net`])
+/// are kept side by side, each one with its own priority.
+///
+/// # Example
+/// ```
+/// # use iptrie::*;
+/// use std::net::Ipv4Addr;
+/// let mut acl = Ipv4RTriePriorityMap::new();
+/// acl.insert_with_priority("10.0.0.0/8".parse().unwrap(), 10);
+/// acl.insert_with_priority("10.1.0.0/16".parse().unwrap(), 1);
+///
+/// assert_eq!( acl.lookup_best(&Ipv4Addr::new(10,1,1,1)), Some((&"10.0.0.0/8".parse().unwrap(), 10)));
+/// assert_eq!( acl.lookup_best(&Ipv4Addr::new(11,1,1,1)), None);
+/// ```
+#[derive(Clone)]
+pub struct RTriePriorityMap<K>(pub(crate) RTrieMap<K,Vec<(K,u32)>>);
+
+/// Convenient alias for radix trie priority map of Ipv4 prefixes
+pub type Ipv4RTriePriorityMap = RTriePriorityMap<Ipv4Prefix>;
+/// Convenient alias for radix trie priority map of Ipv6 prefixes
+pub type Ipv6RTriePriorityMap = RTriePriorityMap<Ipv6Prefix>;
+
+impl<K:IpRootPrefix> RTriePriorityMap<K>
+{
+    /// Creates a new priority map.
+    ///
+    /// The root prefix is present but never matches.
+    #[inline]
+    pub fn new() -> Self { Self::with_capacity(1000) }
+
+    /// Creates a new priority map with a initial capacity.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(RTrieMap::with_root_and_capacity(Vec::new(), capacity))
+    }
+}
+
+impl<K:IpRootPrefix> Default for RTriePriorityMap<K>
+{
+    #[inline] fn default() -> Self { Self::new() }
+}
+
+impl<K:IpPrefix> RTriePriorityMap<K>
+{
+    /// Returns the number of distinct prefix ranges (the root prefix included).
+    #[inline]
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> NonZeroUsize { self.0.len() }
+
+    /// Inserts a prefix with its priority.
+    ///
+    /// If the very same prefix is already present, its priority is updated.
+    pub fn insert_with_priority(&mut self, k: K, priority: u32)
+        where K: PartialEq
+    {
+        match self.0.get_mut(&k) {
+            None => { self.0.insert(k, vec![(k, priority)]); }
+            Some(entries) => match entries.iter_mut().find(|(p,_)| *p == k) {
+                Some(entry) => entry.1 = priority,
+                None => entries.push((k, priority)),
+            }
+        }
+    }
+
+    /// Gets the covering prefix with the highest priority.
+    ///
+    /// For equal priorities, the longest prefix wins (then the first inserted one).
+    /// Returns `None` if no prefix (except the root one) covers the key.
+    pub fn lookup_best<Q>(&self, k: &Q) -> Option<(&K, u32)>
+        where
+            Q: IpPrefix<Addr=K::Addr>,
+            K: IpPrefixCovering<Q>
+    {
+        let trie = &self.0.0;
+        // all the prefixes of a leaf have the same range, so they all cover the key
+        trie.covering_leaves(k)
+            .flat_map(|l| trie.leaves[l].get().1.iter())
+            .reduce(|best, e| if e.1 > best.1 { e } else { best })
+            .map(|(p, priority)| (p, *priority))
+    }
+
+    /// Iterates over all the prefixes with their priority.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item=(&K,u32)> + '_ {
+        self.0.iter().flat_map(|(_,entries)| entries.iter().map(|(p, priority)| (p, *priority)))
+    }
+}
+
+impl<K:IpPrefix+PartialEq> Extend<(K, u32)> for RTriePriorityMap<K>
+{
+    fn extend<I: IntoIterator<Item=(K,u32)>>(&mut self, iter: I)
+    {
+        iter.into_iter().for_each(|(k,priority)| self.insert_with_priority(k,priority))
+    }
+}
+
+impl<K:IpRootPrefix+PartialEq> FromIterator<(K, u32)> for RTriePriorityMap<K>
+{
+    fn from_iter<I:IntoIterator<Item=(K,u32)>>(iter: I) -> Self
+    {
+        let mut trie = Self::default();
+        trie.extend(iter);
+        trie
+    }
+}
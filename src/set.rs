@@ -1,11 +1,19 @@
-use std::num::NonZeroUsize;
+use core::{fmt, slice};
+use core::cmp::Ordering;
+use core::net::{Ipv4Addr, Ipv6Addr};
+use core::num::NonZeroUsize;
+#[cfg(any(test, debug_assertions))]
+use alloc::string::String;
+use alloc::vec::Vec;
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
 use crate::trie::patricia::RadixTrie;
-use crate::trie::lctrie::LevelCompressedTrie;
+use crate::trie::lctrie::{CompressError, CompressionReport, LevelCompressedTrie};
+use crate::trie::common::CapacityError;
 use crate::prefix::*;
 
 #[cfg(feature = "graphviz")] pub use crate::trie::graphviz::DotWriter;
 #[cfg(feature = "graphviz")] use std::fmt::Display;
-use crate::trie::common::Leaf;
+use crate::trie::common::{cmp_prefixes, Leaf, LeafIndex};
 
 /// A set of Ip prefixes based on a radix binary trie
 #[derive(Clone)]
@@ -17,6 +25,9 @@ pub type Ipv4RTrieSet = RTrieSet<Ipv4Prefix>;
 pub type Ipv6RTrieSet = RTrieSet<Ipv6Prefix>;
 
 /// A set of Ip prefixes based on a level-compressed trie
+///
+/// This set is `Send` and `Sync`, so it could be shared between threads
+/// (for instance, in an `Arc`) to perform concurrent lookups.
 pub struct LCTrieSet<P: IpPrefix>(pub(crate) LevelCompressedTrie<P,()>);
 
 /// Convenient alias for LC-Trie set of Ipv4 prefixes
@@ -24,6 +35,237 @@ pub type Ipv4LCTrieSet = LCTrieSet<Ipv4Prefix>;
 /// Convenient alias for LC-Trie set of Ipv6 prefixes
 pub type Ipv6LCTrieSet = LCTrieSet<Ipv6Prefix>;
 
+/// An iterator over the prefixes of a set
+///
+/// See [`RTrieSet::iter`] and [`LCTrieSet::iter`].
+pub struct SetIter<'a,P>(slice::Iter<'a, Leaf<P,()>>);
+
+impl<'a,P> Iterator for SetIter<'a,P> {
+    type Item = &'a P;
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> { self.0.next().map(Leaf::prefix) }
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) { self.0.size_hint() }
+}
+
+impl<P> ExactSizeIterator for SetIter<'_,P> {}
+
+/// The matches of a key in a set
+///
+/// See [`RTrieSet::classify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<'a,P> {
+    exact: Option<&'a P>,
+    longest: &'a P,
+}
+
+impl<'a,P> Match<'a,P> {
+    /// The prefix which matches exactly the key, if any
+    #[inline]
+    pub fn exact(&self) -> Option<&'a P> { self.exact }
+
+    /// The longest prefix which matches the key
+    #[inline]
+    pub fn longest(&self) -> &'a P { self.longest }
+}
+
+/// The outcome of a prefix insertion in a set
+///
+/// See [`RTrieSet::would_insert`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertOutcome<'a,P> {
+    /// The prefix is not present and would be inserted
+    New,
+    /// A prefix with the same range (but different host bits) is present,
+    /// so the insertion would do nothing
+    EquivalentExists(&'a P),
+    /// The very same prefix is present, so the insertion would do nothing
+    ExactExists,
+}
+
+/// The behavior when inserting a prefix equivalent to an existing one
+///
+/// See [`RTrieSet::insert_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InsertMode {
+    /// The existing prefix is kept (as [`RTrieSet::insert`])
+    #[default]
+    KeepExisting,
+    /// The existing prefix is overwritten by the new one (as [`RTrieSet::replace`])
+    Overwrite,
+}
+
+/// The event reported when inserting a prefix in a set
+///
+/// See [`RTrieSet::insert_reporting`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertEvent<P> {
+    /// The prefix was not covered by any other prefix (except the root one)
+    New,
+    /// A prefix with the same range (possibly the very same prefix) was replaced
+    ReplacedEquivalent(P),
+    /// The prefix is redundant since this shorter prefix already covers it
+    ShadowedBy(P),
+}
+
+/// A handle on a prefix of a compressed set
+///
+/// See [`LCTrieSet::lookup_token`].
+pub struct LookupToken<'a,P:IpPrefix> {
+    set: &'a LCTrieSet<P>,
+    leaf: LeafIndex,
+}
+
+impl<'a,P:IpPrefix> LookupToken<'a,P> {
+    /// The prefix identified by this token
+    #[inline]
+    pub fn resolve(&self) -> &'a P { self.set.0.leaves[self.leaf].prefix() }
+
+    /// The position of the prefix inside the set.
+    ///
+    /// It is lower than the size of the set and corresponds
+    /// to the position of the prefix in [`LCTrieSet::iter`].
+    #[inline]
+    pub fn index(&self) -> usize { self.leaf.index() }
+}
+
+impl<P:IpPrefix> Clone for LookupToken<'_,P> {
+    #[inline]
+    fn clone(&self) -> Self { *self }
+}
+
+impl<P:IpPrefix> Copy for LookupToken<'_,P> {}
+
+impl<P:IpPrefix> PartialEq for LookupToken<'_,P> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        core::ptr::eq(self.set, other.set) && self.leaf == other.leaf
+    }
+}
+
+impl<P:IpPrefix> Eq for LookupToken<'_,P> {}
+
+impl<P:IpPrefix> fmt::Debug for LookupToken<'_,P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LookupToken").field(&self.leaf.index()).field(self.resolve()).finish()
+    }
+}
+
+/// A position on a prefix of a set, to navigate along its hierarchy
+///
+/// The parent of a prefix is the longest other prefix of the set which covers it
+/// and its children are the prefixes of which it is the parent.
+/// The root prefix is the parent of all the top-level prefixes.
+///
+/// See [`RTrieSet::cursor_at`].
+pub struct Cursor<'a,P:IpPrefix> {
+    set: &'a RTrieSet<P>,
+    leaf: LeafIndex,
+}
+
+impl<'a,P:IpPrefix> Cursor<'a,P> {
+    #[inline]
+    fn at(&self, leaf: LeafIndex) -> Self { Self { set: self.set, leaf } }
+
+    /// The prefix at the position of this cursor
+    #[inline]
+    pub fn prefix(&self) -> &'a P { &self.set.0[self.leaf] }
+
+    /// Checks if this cursor is positioned at the root prefix.
+    #[inline]
+    pub fn is_root(&self) -> bool { self.leaf.is_root_leaf() }
+
+    /// Moves to the parent prefix (`None` for the root prefix).
+    #[inline]
+    pub fn parent(&self) -> Option<Self> {
+        self.set.0.covering_leaves(self.prefix()).nth(1).map(|l| self.at(l))
+    }
+
+    /// Iterates over the children prefixes, sorted by network address.
+    pub fn children(&self) -> impl Iterator<Item=Cursor<'a,P>> + 'a
+    {
+        let (set, leaf) = (self.set, self.leaf);
+        core::iter::successors(set.0.next_child_leaf(leaf, None), move |&c| set.0.next_child_leaf(leaf, Some(c)))
+            .map(move |leaf| Cursor { set, leaf })
+    }
+
+    /// Moves to the first child prefix (the one with the lowest network address), if any.
+    #[inline]
+    pub fn first_child(&self) -> Option<Self> {
+        self.set.0.next_child_leaf(self.leaf, None).map(|l| self.at(l))
+    }
+
+    /// Moves to the next prefix with the same parent, if any.
+    ///
+    /// The root prefix has no sibling.
+    pub fn next_sibling(&self) -> Option<Self> {
+        let parent = self.parent()?;
+        self.set.0.next_child_leaf(parent.leaf, Some(self.leaf)).map(|l| self.at(l))
+    }
+}
+
+impl<P:IpPrefix> Clone for Cursor<'_,P> {
+    #[inline]
+    fn clone(&self) -> Self { *self }
+}
+
+impl<P:IpPrefix> Copy for Cursor<'_,P> {}
+
+impl<P:IpPrefix> PartialEq for Cursor<'_,P> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        core::ptr::eq(self.set, other.set) && self.leaf == other.leaf
+    }
+}
+
+impl<P:IpPrefix> Eq for Cursor<'_,P> {}
+
+impl<P:IpPrefix> fmt::Debug for Cursor<'_,P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Cursor").field(self.prefix()).finish()
+    }
+}
+
+/// Output format of the prefixes when exporting a set
+///
+/// See [`RTrieSet::export`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A prefix per line, as `10.0.0.0/8`
+    Cidr,
+    /// A range of addresses per line, as `10.0.0.0-10.255.255.255`
+    Range,
+}
+
+/// Error generated when parsing a JSON array of prefixes
+///
+/// See [`RTrieSet::from_json`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonError {
+    /// The input is not an array of strings
+    Syntax,
+    /// A string of the array is not a valid prefix
+    Prefix(IpPrefixError),
+}
+
+impl fmt::Display for JsonError
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonError::Syntax => fmt.write_str("invalid JSON array of strings"),
+            JsonError::Prefix(e) => write!(fmt, "invalid prefix in JSON array: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for JsonError {}
+
+impl From<IpPrefixError> for JsonError {
+    #[inline]
+    fn from(e: IpPrefixError) -> Self { JsonError::Prefix(e) }
+}
+
 impl<P:IpRootPrefix> RTrieSet<P>
 {
     /// Creates a new set which contains the root prefix.
@@ -35,6 +277,74 @@ impl<P:IpRootPrefix> RTrieSet<P>
     /// The returned set already contains the root prefix.
     #[inline]
     pub fn with_capacity(capacity: usize) -> Self { Self(RadixTrie::new((), capacity)) }
+
+    /// Creates a new set with initial capacities for the prefixes and the branching nodes.
+    ///
+    /// By default (see [`Self::with_capacity`]), there is room for half
+    /// as many branching nodes as prefixes, which could be too few for sparse sets.
+    /// The returned set already contains the root prefix.
+    #[inline]
+    pub fn with_capacities(leaves: usize, branching: usize) -> Self {
+        Self(RadixTrie::with_capacities((), leaves, branching))
+    }
+
+    /// Collects the prefixes, keeping only the ones which are not covered by another one.
+    ///
+    /// This is the same as collecting then calling [`Self::dedup_covered`].
+    /// Notice that adjacent prefixes are not merged.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from_iter_aggregated(
+    ///     ["10.1.0.0/16", "10.0.0.0/8", "10.1.1.0/24", "1.1.1.0/24"].map(|p| p.parse::<Ipv4Prefix>().unwrap()));
+    /// assert_eq!( trie.len().get(), 3);
+    /// assert!( trie.contains(&"10.0.0.0/8".parse::<Ipv4Prefix>().unwrap()));
+    /// assert!( trie.contains(&"1.1.1.0/24".parse::<Ipv4Prefix>().unwrap()));
+    /// ```
+    pub fn from_iter_aggregated<I:IntoIterator<Item=P>>(iter: I) -> Self
+    {
+        let mut trie = Self::from_iter(iter);
+        trie.dedup_covered();
+        trie
+    }
+
+    /// Reads a set from a JSON array of prefixes, as written by [`Self::write_json`].
+    ///
+    /// Only an array of strings without escape sequences is accepted
+    /// (which is enough for prefixes).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from_json(r#"[ "10.0.0.0/8", "1.1.1.0/24" ]"#).unwrap();
+    /// assert_eq!( trie.len().get(), 3);
+    ///
+    /// assert_eq!( Ipv4RTrieSet::from_json(r#"[ "10.0.0.0/8", ]"#).err(), Some(JsonError::Syntax));
+    /// assert_eq!( Ipv4RTrieSet::from_json(r#"[ "10.0.0.256/8" ]"#).err(), Some(JsonError::Prefix(IpPrefixError::AddrParseError)));
+    /// ```
+    pub fn from_json(s: &str) -> Result<Self, JsonError>
+        where P: core::str::FromStr<Err=IpPrefixError>
+    {
+        let items = s.trim()
+            .strip_prefix('[')
+            .and_then(|s| s.strip_suffix(']'))
+            .ok_or(JsonError::Syntax)?;
+        let mut trie = Self::new();
+        if items.trim().is_empty() {
+            return Ok(trie);
+        }
+        // a prefix never contains a comma so the array could be simply split
+        for item in items.split(',') {
+            let prefix = item.trim()
+                .strip_prefix('"')
+                .and_then(|s| s.strip_suffix('"'))
+                .filter(|s| !s.contains(['"', '\\']))
+                .ok_or(JsonError::Syntax)?;
+            trie.insert(prefix.parse()?);
+        }
+        Ok(trie)
+    }
 }
 
 impl<P:IpPrefix> RTrieSet<P>
@@ -47,6 +357,19 @@ impl<P:IpPrefix> RTrieSet<P>
     #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> NonZeroUsize { self.0.len() }
 
+    /// Checks if the set contains only the root prefix.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut trie = Ipv4RTrieSet::new();
+    /// assert!( trie.is_empty() );
+    /// trie.insert("1.1.0.0/16".parse().unwrap());
+    /// assert!( !trie.is_empty() );
+    /// ```
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.0.len().get() == 1 }
+
     /// Compress this Patricia trie in a LC-Trie.
     ///
     /// For lookup algorithms, a Patricia trie performs unit bit checking and LC-Trie
@@ -55,6 +378,41 @@ impl<P:IpPrefix> RTrieSet<P>
     #[inline]
     pub fn compress(self) -> LCTrieSet<P> { LCTrieSet(LevelCompressedTrie::new(self.0)) }
 
+    /// Compress this Patricia trie in a LC-Trie and checks the result.
+    ///
+    /// Same as [`Self::compress`] but the compressed trie is checked afterwards:
+    /// each leaf should be reachable exactly once and the lookup of each stored prefix
+    /// should return itself. If not, an error describing the inconsistency is returned.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from_iter(["1.1.0.0/20".parse::<Ipv4Prefix>().unwrap()]);
+    /// let lctrie = trie.try_compress().unwrap();
+    /// assert_eq!( lctrie.len().get(), 2);
+    /// ```
+    pub fn try_compress(self) -> Result<LCTrieSet<P>, CompressError>
+    {
+        let lctrie = LevelCompressedTrie::new(self.0);
+        lctrie.check()?;
+        Ok(LCTrieSet(lctrie))
+    }
+
+    /// Predicts the memory size (in bytes) of the compressed version of this trie.
+    ///
+    /// The compressed nodes are sized by the same pass as the compression itself
+    /// (which is not performed) so the result is exactly the one given by
+    /// [`LCTrieSet::memory_bytes`] after the compression.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from_iter(["1.1.0.0/16".parse::<Ipv4Prefix>().unwrap(), "1.1.1.0/24".parse().unwrap()]);
+    /// let estimated = trie.estimated_compressed_bytes();
+    /// assert_eq!( trie.compress().memory_bytes(), estimated);
+    /// ```
+    pub fn estimated_compressed_bytes(&self) -> usize { LevelCompressedTrie::estimated_bytes(&self.0) }
+
     #[inline]
     pub fn shrink_to_fit(&mut self) { self.0.shrink_to_fit() }
 
@@ -83,6 +441,51 @@ impl<P:IpPrefix> RTrieSet<P>
         self.0.insert(k,()).is_none()
     }
 
+    /// Inserts a new element in the set, checking the capacity of the trie.
+    ///
+    /// It behaves as [`Self::insert`] but returns an error, instead of panicking,
+    /// if the trie has reached its maximal number of nodes (about 2<sup>31</sup>).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut trie = Ipv4RTrieSet::new();
+    ///
+    /// assert_eq!( trie.try_insert("1.1.0.0/16".parse().unwrap()), Ok(true));
+    /// assert_eq!( trie.try_insert("1.1.0.0/16".parse().unwrap()), Ok(false));
+    /// ```
+    #[inline]
+    pub fn try_insert(&mut self, k: P) -> Result<bool, CapacityError>
+    {
+        self.0.try_insert(k,()).map(|v| v.is_none())
+    }
+
+    /// Checks the outcome of the insertion of a prefix, without inserting it.
+    ///
+    /// Since prefixes with the same range are considered equal, inserting
+    /// a prefix which differs only by its host bits (as for [`ipnet::Ipv4Net`])
+    /// from an existing one does nothing.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use ipnet::Ipv4Net;
+    /// let trie = RTrieSet::<Ipv4Net>::from_iter(["10.1.1.1/24".parse().unwrap()]);
+    ///
+    /// assert_eq!( trie.would_insert(&"10.1.1.1/24".parse().unwrap()), InsertOutcome::ExactExists);
+    /// assert_eq!( trie.would_insert(&"10.1.1.2/24".parse().unwrap()), InsertOutcome::EquivalentExists(&"10.1.1.1/24".parse().unwrap()));
+    /// assert_eq!( trie.would_insert(&"10.1.1.2/25".parse().unwrap()), InsertOutcome::New);
+    /// ```
+    pub fn would_insert(&self, k: &P) -> InsertOutcome<'_,P>
+        where P: IpPrefixCovering<P> + PartialEq
+    {
+        match self.get(k) {
+            None => InsertOutcome::New,
+            Some(p) if p == k => InsertOutcome::ExactExists,
+            Some(p) => InsertOutcome::EquivalentExists(p),
+        }
+    }
+
     /// Checks if an element is present (exact match).
     ///
     /// # Example
@@ -144,6 +547,31 @@ impl<P:IpPrefix> RTrieSet<P>
         self.0.remove(k).is_some()
     }
 
+    /// Removes all the given prefixes (exact match).
+    ///
+    /// The absent prefixes are skipped and the root prefix is never removed.
+    /// Returns the number of prefixes which were effectively removed.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut trie = Ipv4RTrieSet::from_iter(["10.0.0.0/8", "10.1.0.0/16", "1.1.1.0/24"].map(|p| p.parse().unwrap()));
+    ///
+    /// let removed = trie.remove_all(["10.1.0.0/16", "10.2.0.0/16", "0.0.0.0/0"].map(|p| p.parse::<Ipv4Prefix>().unwrap()));
+    /// assert_eq!( removed, 1);
+    /// assert_eq!( trie.len().get(), 3);
+    /// ```
+    pub fn remove_all<Q, I: IntoIterator<Item=Q>>(&mut self, iter: I) -> usize
+        where
+            Q: IpPrefix<Addr=P::Addr>,
+            P: IpPrefixCovering<Q>
+    {
+        iter.into_iter()
+            .filter(|k| k.len() > 0) // never remove the root prefix
+            .filter(|k| self.remove(k))
+            .count()
+    }
+
     /// Adds a prefix to the set, replacing the existing one, if any (exact match performed).
     /// Returns the replaced value.
     ///
@@ -174,6 +602,67 @@ impl<P:IpPrefix> RTrieSet<P>
         self.0.replace(k,()).map(|l| *l.prefix())
     }
 
+    /// Inserts a new element in the set, choosing what to do with an equivalent one.
+    ///
+    /// If a prefix with the same range (but possibly different host bits)
+    /// already exists, it is kept or overwritten according to the mode
+    /// and `false` is returned.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use ipnet::Ipv4Net;
+    /// let mut trie = RTrieSet::<Ipv4Net>::new();
+    /// trie.insert("1.1.1.1/20".parse().unwrap());
+    ///
+    /// assert!( !trie.insert_mode("1.1.1.2/20".parse().unwrap(), InsertMode::KeepExisting));
+    /// assert_eq!( trie.get(&"1.1.0.0/20".parse::<Ipv4Net>().unwrap()).unwrap().to_string(), "1.1.1.1/20");
+    ///
+    /// assert!( !trie.insert_mode("1.1.1.2/20".parse().unwrap(), InsertMode::Overwrite));
+    /// assert_eq!( trie.get(&"1.1.0.0/20".parse::<Ipv4Net>().unwrap()).unwrap().to_string(), "1.1.1.2/20");
+    /// ```
+    #[inline]
+    pub fn insert_mode(&mut self, k: P, mode: InsertMode) -> bool
+    {
+        match mode {
+            InsertMode::KeepExisting => self.insert(k),
+            InsertMode::Overwrite => self.replace(k).is_none(),
+        }
+    }
+
+    /// Inserts a prefix in the set and reports how it fits with the existing ones.
+    ///
+    /// The prefix is always inserted, replacing an equivalent one (as [`Self::replace`]).
+    /// Exactly one event is reported: a replacement takes precedence over a shadowing
+    /// (the root prefix never shadows anything).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut trie = Ipv4RTrieSet::new();
+    /// let mut events = vec![];
+    ///
+    /// ["10.0.0.0/8", "10.1.0.0/16", "10.0.0.0/8"].into_iter()
+    ///     .for_each(|p| trie.insert_reporting(p.parse().unwrap(), |e| events.push(e)));
+    ///
+    /// assert_eq!( events, [
+    ///     InsertEvent::New,
+    ///     InsertEvent::ShadowedBy("10.0.0.0/8".parse().unwrap()),
+    ///     InsertEvent::ReplacedEquivalent("10.0.0.0/8".parse().unwrap())]);
+    /// ```
+    pub fn insert_reporting<F: FnMut(InsertEvent<P>)>(&mut self, k: P, mut f: F)
+    {
+        let shadow = self.0.covering_leaves(&k)
+            .find(|&l| self.0[l].len() < k.len())
+            .filter(|l| !l.is_root_leaf())
+            .map(|l| self.0[l]);
+        f(match (self.replace(k), shadow) {
+            (Some(old), _) => InsertEvent::ReplacedEquivalent(old),
+            (None, Some(shadow)) => InsertEvent::ShadowedBy(shadow),
+            (None, None) => InsertEvent::New,
+        })
+    }
+
 
     /// Gets the value associated with an exact match of the key.
     ///
@@ -242,56 +731,1275 @@ impl<P:IpPrefix> RTrieSet<P>
         self.0.lookup(k).0
     }
 
-    /// Iterates over all the prefixes of this set.
-    #[inline]
-    pub fn iter(&self) -> impl Iterator<Item=&P> + '_ {
-        self.0.leaves.0.iter().map(Leaf::prefix)
-    }
-
-    #[inline]
-    pub fn info(&self) { self.0.info() }
-}
-
-impl<P:IpRootPrefix> Default for RTrieSet<P>
-{
+    /// Gets the longest prefix match of the key, falling back to another set
+    /// if only the root prefix matches.
+    ///
+    /// This chains layered tables (a specific one which overrides a default one)
+    /// without merging them. The root prefix of the fallback set is returned
+    /// if none of them matches.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv4Addr;
+    /// let specific = Ipv4RTrieSet::from_iter(["10.1.0.0/16".parse().unwrap()]);
+    /// let default = Ipv4RTrieSet::from_iter(["10.0.0.0/8".parse().unwrap(), "192.168.0.0/16".parse().unwrap()]);
+    ///
+    /// assert_eq!( specific.lookup_or(&Ipv4Addr::new(10,1,1,1), &default).to_string(), "10.1.0.0/16");
+    /// assert_eq!( specific.lookup_or(&Ipv4Addr::new(10,2,1,1), &default).to_string(), "10.0.0.0/8");
+    /// assert_eq!( specific.lookup_or(&Ipv4Addr::new(192,168,1,1), &default).to_string(), "192.168.0.0/16");
+    /// assert_eq!( specific.lookup_or(&Ipv4Addr::new(1,1,1,1), &default).to_string(), "0.0.0.0/0");
+    /// ```
     #[inline]
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl<P:IpPrefix> Extend<P> for RTrieSet<P>
-{
-    fn extend<I: IntoIterator<Item=P>>(&mut self, iter: I)
+    pub fn lookup_or<'a, Q>(&'a self, k: &Q, fallback: &'a RTrieSet<P>) -> &'a P
+        where
+            Q: IpPrefix<Addr=P::Addr>,
+            P: IpPrefixCovering<Q>
     {
-        iter.into_iter().for_each(| item | { self.insert(item); } )
+        match self.lookup(k) {
+            p if p.len() == 0 => fallback.lookup(k), // only the root prefix matches
+            p => p
+        }
     }
-}
 
-impl<P:IpRootPrefix> FromIterator<P> for RTrieSet<P>
-{
-    fn from_iter<I:IntoIterator<Item=P>>(iter: I) -> Self
+    /// Gets a copy of the longest prefix which matches the given key.
+    ///
+    /// Same as [`Self::lookup`] but the result does not borrow the set
+    /// (which could then be modified).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv4Addr;
+    /// let mut trie = Ipv4RTrieSet::from(["1.1.0.0/16".parse().unwrap()]);
+    ///
+    /// let found = trie.lookup_owned(&Ipv4Addr::new(1,1,1,1));
+    /// assert_eq!( &found, trie.lookup(&Ipv4Addr::new(1,1,1,1)));
+    /// trie.remove(&found);
+    /// assert_eq!( trie.lookup_owned(&Ipv4Addr::new(1,1,1,1)).to_string(), "0.0.0.0/0");
+    /// ```
+    #[inline]
+    pub fn lookup_owned<Q>(&self, k: &Q) -> P
+        where
+            Q: IpPrefix<Addr=P::Addr>,
+            P: IpPrefixCovering<Q>
     {
-        let mut trieset = Self::default();
-        trieset.extend(iter);
-        trieset
+        *self.lookup(k)
     }
-}
 
-impl<P:IpPrefix> LCTrieSet<P>
-{
-    /// Returns the size of the set.
+    /// Gets the longest prefix which matches the given key and checks if it is not the root one.
     ///
-    /// Notice that it never equals zero since the top prefix is
-    /// always present in the set.
-    #[inline]
-    #[allow(clippy::len_without_is_empty)]
-    pub fn len(&self) -> NonZeroUsize { self.0.len() }
-
-    #[inline]
-    pub fn info(&self) { self.0.info() }
-
-    /// Checks if an element is present (exact match).
+    /// The boolean is `true` if a prefix other than the root one matches.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv4Addr;
+    /// let trie = Ipv4RTrieSet::from(["1.1.1.0/24".parse().unwrap()]);
+    ///
+    /// assert_eq!( trie.lookup_checked(&Ipv4Addr::new(1,1,1,1)), (&"1.1.1.0/24".parse().unwrap(), true));
+    /// assert_eq!( trie.lookup_checked(&Ipv4Addr::new(2,2,2,2)), (&Ipv4Prefix::root(), false));
+    /// ```
+    #[inline]
+    pub fn lookup_checked<Q>(&self, k: &Q) -> (&P, bool)
+        where
+            Q: IpPrefix<Addr=P::Addr>,
+            P: IpPrefixCovering<Q>
+    {
+        let found = self.lookup(k);
+        (found, found.len() > 0)
+    }
+
+    /// Gets both the exact and the longest prefix matches of the given key.
+    ///
+    /// Only one lookup is performed.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from(["1.1.1.0/24".parse().unwrap()]);
+    ///
+    /// let ip24 = "1.1.1.0/24".parse::<Ipv4Prefix>().unwrap();
+    /// let ip25 = "1.1.1.0/25".parse::<Ipv4Prefix>().unwrap();
+    ///
+    /// let m = trie.classify(&ip25);
+    /// assert_eq!( m.exact(), None);
+    /// assert_eq!( m.longest(), &ip24);
+    ///
+    /// let m = trie.classify(&ip24);
+    /// assert_eq!( m.exact(), Some(&ip24));
+    /// assert_eq!( m.longest(), &ip24);
+    /// ```
+    #[inline]
+    pub fn classify<Q>(&self, k: &Q) -> Match<'_,P>
+        where
+            Q: IpPrefix<Addr=P::Addr>,
+            P: IpPrefixCovering<Q>
+    {
+        let longest = self.lookup(k);
+        Match { exact: (longest.len() == k.len()).then_some(longest), longest }
+    }
+
+    /// Gets the longest prefix which matches the given key, except the root prefix.
+    ///
+    /// Same as [`Self::lookup`] but returns `None` if the only matching prefix
+    /// is the root one.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv4Addr;
+    /// let trie = Ipv4RTrieSet::from(["1.1.1.0/24".parse().unwrap()]);
+    ///
+    /// assert_eq!( trie.nearest(&Ipv4Addr::new(1,1,1,1)).map(|p| p.to_string()), Some("1.1.1.0/24".to_string()));
+    /// assert_eq!( trie.nearest(&Ipv4Addr::new(2,2,2,2)), None);
+    /// ```
+    #[inline]
+    pub fn nearest<Q>(&self, k: &Q) -> Option<&P>
+        where
+            Q: IpPrefix<Addr=P::Addr>,
+            P: IpPrefixCovering<Q>
+    {
+        Some(self.lookup(k)).filter(|p| p.len() > 0)
+    }
+
+    /// Checks if the given key overlaps a prefix of this set, the root prefix excepted.
+    ///
+    /// The key overlaps a prefix if it is covered by it or if it covers it.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv4Addr;
+    /// let trie = Ipv4RTrieSet::from(["10.1.0.0/16".parse().unwrap()]);
+    ///
+    /// assert!( trie.overlaps_any(&"10.1.1.0/24".parse::<Ipv4Prefix>().unwrap()));
+    /// assert!( trie.overlaps_any(&"10.0.0.0/8".parse::<Ipv4Prefix>().unwrap()));
+    /// assert!( !trie.overlaps_any(&"10.2.0.0/16".parse::<Ipv4Prefix>().unwrap()));
+    /// ```
+    pub fn overlaps_any<Q>(&self, k: &Q) -> bool
+        where
+            Q: IpPrefix<Addr=P::Addr> + IpPrefixCovering<P>,
+            P: IpPrefixCovering<Q>
+    {
+        self.nearest(k).is_some() || self.0.covers_any_leaf(k)
+    }
+
+    /// Gets the greatest prefix of this set which is lower than or equal to the key.
+    ///
+    /// The prefixes are ordered by network address, then by length
+    /// (so this is not a coverage query). The root prefix is not considered.
+    ///
+    /// All the prefixes are scanned, so the complexity is linear.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from(["10.0.0.0/24".parse().unwrap(), "10.0.2.0/24".parse().unwrap()]);
+    ///
+    /// assert_eq!( trie.floor(&"10.0.1.0/24".parse::<Ipv4Prefix>().unwrap()).map(|p| p.to_string()), Some("10.0.0.0/24".to_string()));
+    /// assert_eq!( trie.floor(&"10.0.0.0/23".parse::<Ipv4Prefix>().unwrap()), None);
+    /// ```
+    pub fn floor<Q>(&self, k: &Q) -> Option<&P>
+        where Q: IpPrefix<Slot=P::Slot>
+    {
+        self.iter()
+            .skip(1) // skip the root prefix
+            .filter(|p| cmp_prefixes(*p, k) != Ordering::Greater)
+            .max_by(|a, b| cmp_prefixes(*a, *b))
+    }
+
+    /// Gets the smallest prefix of this set which is greater than or equal to the key.
+    ///
+    /// The prefixes are ordered by network address, then by length
+    /// (so this is not a coverage query). The root prefix is not considered.
+    ///
+    /// All the prefixes are scanned, so the complexity is linear.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from(["10.0.0.0/24".parse().unwrap(), "10.0.2.0/24".parse().unwrap()]);
+    ///
+    /// assert_eq!( trie.ceiling(&"10.0.1.0/24".parse::<Ipv4Prefix>().unwrap()).map(|p| p.to_string()), Some("10.0.2.0/24".to_string()));
+    /// assert_eq!( trie.ceiling(&"10.0.2.0/25".parse::<Ipv4Prefix>().unwrap()), None);
+    /// ```
+    pub fn ceiling<Q>(&self, k: &Q) -> Option<&P>
+        where Q: IpPrefix<Slot=P::Slot>
+    {
+        self.iter()
+            .skip(1) // skip the root prefix
+            .filter(|p| cmp_prefixes(*p, k) != Ordering::Less)
+            .min_by(|a, b| cmp_prefixes(*a, *b))
+    }
+
+    /// Counts the prefixes of this set which cover the given key, the root prefix excepted.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv4Addr;
+    /// let trie = Ipv4RTrieSet::from_iter(["1.0.0.0/8", "1.1.0.0/16", "1.1.1.0/24"].map(|p| p.parse().unwrap()));
+    ///
+    /// assert_eq!( trie.coverage_depth(&Ipv4Addr::new(1,1,1,1)), 3);
+    /// assert_eq!( trie.coverage_depth(&Ipv4Addr::new(1,2,1,1)), 1);
+    /// assert_eq!( trie.coverage_depth(&Ipv4Addr::new(2,2,2,2)), 0);
+    /// ```
+    pub fn coverage_depth<Q>(&self, k: &Q) -> usize
+        where
+            Q: IpPrefix<Addr=P::Addr>,
+            P: IpPrefixCovering<Q>
+    {
+        self.0.covering_leaves(k).count() - 1 // skip the root prefix
+    }
+
+    /// Gets the most specific prefixes which match the given key.
+    ///
+    /// At most `max` prefixes are returned, from the longest to the shortest one.
+    /// The root prefix comes last, so it is returned only if there are
+    /// less than `max` other matching prefixes.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv4Addr;
+    /// let trie = Ipv4RTrieSet::from_iter(["1.0.0.0/8", "1.1.0.0/16", "1.1.1.0/24"].map(|p| p.parse().unwrap()));
+    ///
+    /// let top = trie.lookup_top_k(&Ipv4Addr::new(1,1,1,1), 2);
+    /// assert_eq!( top.iter().map(|p| p.to_string()).collect::<Vec<_>>(), ["1.1.1.0/24", "1.1.0.0/16"]);
+    ///
+    /// let top = trie.lookup_top_k(&Ipv4Addr::new(1,2,1,1), 3);
+    /// assert_eq!( top.iter().map(|p| p.to_string()).collect::<Vec<_>>(), ["1.0.0.0/8", "0.0.0.0/0"]);
+    /// ```
+    pub fn lookup_top_k<Q>(&self, k: &Q, max: usize) -> Vec<&P>
+        where
+            Q: IpPrefix<Addr=P::Addr>,
+            P: IpPrefixCovering<Q>
+    {
+        self.0.covering_leaves(k)
+            .take(max)
+            .map(|l| &self.0[l])
+            .collect()
+    }
+
+    /// Iterates over all the prefixes of this set.
+    ///
+    /// The root prefix comes first but the order of the others is not specified
+    /// and may change in future versions. For a documented order,
+    /// use [`Self::iter_insertion_order`].
+    #[inline]
+    pub fn iter(&self) -> SetIter<'_,P> {
+        SetIter(self.0.leaves.0.iter())
+    }
+
+    /// Iterates over all the prefixes of this set, except the root one.
+    ///
+    /// The root prefix is implicit: it is always in the set, even if not inserted.
+    /// The order is the one of [`Self::iter`].
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from(["10.0.0.0/8".parse().unwrap(), "1.1.0.0/16".parse().unwrap()]);
+    /// assert_eq!( trie.iter().count(), 3);
+    /// assert_eq!( trie.iter_non_root().map(|p| p.to_string()).collect::<Vec<_>>(), ["10.0.0.0/8", "1.1.0.0/16"]);
+    /// ```
+    #[inline]
+    pub fn iter_non_root(&self) -> SetIter<'_,P> {
+        SetIter(self.0.leaves.0[1..].iter()) // the root prefix is always the first one
+    }
+
+    /// Iterates over all the prefixes of this set in insertion order.
+    ///
+    /// The root prefix comes first, then the others in insertion order.
+    /// Notice that removing a prefix moves the last one at its place.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut trie = Ipv4RTrieSet::new();
+    /// ["10.0.0.0/8", "1.1.0.0/16", "10.1.0.0/16"].iter()
+    ///     .for_each(|p| { trie.insert(p.parse().unwrap()); });
+    ///
+    /// assert_eq!( trie.iter_insertion_order().map(|p| p.to_string()).collect::<Vec<_>>(),
+    ///     ["0.0.0.0/0", "10.0.0.0/8", "1.1.0.0/16", "10.1.0.0/16"]);
+    ///
+    /// trie.remove(&"10.0.0.0/8".parse::<Ipv4Prefix>().unwrap());
+    /// assert_eq!( trie.iter_insertion_order().map(|p| p.to_string()).collect::<Vec<_>>(),
+    ///     ["0.0.0.0/0", "10.1.0.0/16", "1.1.0.0/16"]);
+    /// ```
+    #[inline]
+    pub fn iter_insertion_order(&self) -> SetIter<'_,P> {
+        SetIter(self.0.leaves.0.iter())
+    }
+
+    /// Removes all the prefixes of this set (except the root one) and iterates over them.
+    ///
+    /// The allocated memory is kept and the set remains usable.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut trie = Ipv4RTrieSet::from(["1.1.0.0/16".parse().unwrap(), "1.2.0.0/16".parse().unwrap()]);
+    /// assert_eq!( trie.drain().count(), 2);
+    /// assert!( trie.is_empty() );
+    ///
+    /// trie.insert("1.3.0.0/16".parse().unwrap());
+    /// assert_eq!( trie.len().get(), 2);
+    /// ```
+    #[inline]
+    pub fn drain(&mut self) -> impl Iterator<Item=P> + '_ {
+        self.0.drain().map(|(p,_)| p)
+    }
+
+    /// Iterates over all the prefixes of this set, converted to [`IpNet`].
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use ipnet::IpNet;
+    /// let trie = Ipv4RTrieSet::from_iter(["1.1.0.0/20".parse::<Ipv4Prefix>().unwrap()]);
+    ///
+    /// let nets = trie.iter_ipnet().collect::<Vec<IpNet>>();
+    /// assert_eq!( nets, ["0.0.0.0/0".parse::<IpNet>().unwrap(), "1.1.0.0/20".parse().unwrap()]);
+    /// ```
+    #[inline]
+    pub fn iter_ipnet(&self) -> impl Iterator<Item=IpNet> + '_
+        where P: Into<IpNet>
+    {
+        self.iter().map(|p| (*p).into())
+    }
+
+    /// Computes the addresses of this set which are not covered by the other one.
+    ///
+    /// The result is a set of prefixes which covers exactly the addresses covered
+    /// by a prefix of this set but not covered by any prefix of the other set.
+    /// The root prefixes are not considered.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let a = Ipv4RTrieSet::from(["10.0.0.0/8".parse().unwrap()]);
+    /// let b = Ipv4RTrieSet::from(["10.1.0.0/16".parse().unwrap()]);
+    ///
+    /// let diff = a.address_difference(&b);
+    /// assert_eq!( diff.iter().skip(1).map(|p| p.to_string()).collect::<Vec<_>>(),
+    ///     ["10.0.0.0/16", "10.2.0.0/15", "10.4.0.0/14", "10.8.0.0/13",
+    ///      "10.16.0.0/12", "10.32.0.0/11", "10.64.0.0/10", "10.128.0.0/9"]);
+    /// ```
+    pub fn address_difference(&self, other: &RTrieSet<P>) -> RTrieSet<P>
+        where P: IpRootPrefix + IpPrefixFromSlot
+    {
+        let mut diff = RTrieSet::new();
+        self.top_prefixes()
+            .for_each(|p| other.0.uncovered(*p, &mut |p| { diff.insert(p); }));
+        diff
+    }
+
+    /// Finds the first free block of the given length located after a prefix.
+    ///
+    /// The returned prefix is the first one (in address order) of length `len`
+    /// which starts after the last address of `after` and which does not intersect
+    /// any prefix of this set (the root prefix excepted).
+    /// Returns `None` if there is no such block or if `len` is too large.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from([
+    ///     "10.0.0.0/24".parse().unwrap(),
+    ///     "10.0.1.0/24".parse().unwrap(),
+    ///     "10.0.3.128/25".parse().unwrap()]);
+    ///
+    /// let after = "10.0.0.0/24".parse().unwrap();
+    /// assert_eq!( trie.next_free(after, 24).unwrap().to_string(), "10.0.2.0/24");
+    /// let after = "10.0.2.0/24".parse().unwrap();
+    /// assert_eq!( trie.next_free(after, 24).unwrap().to_string(), "10.0.4.0/24");
+    /// assert_eq!( trie.next_free(after, 25).unwrap().to_string(), "10.0.3.0/25");
+    /// ```
+    pub fn next_free(&self, after: P, len: u8) -> Option<P>
+        where P: IpRootPrefix + IpPrefixFromSlot
+    {
+        if len > P::MAX_LEN { return None; }
+        self.0.first_free(P::root(), &after, len)
+    }
+
+    /// Checks if all the addresses of this set are covered by the other one.
+    ///
+    /// An address could be covered by any prefix of the other set (so a prefix of
+    /// this set could be covered by several ones). The root prefixes are not considered.
+    /// This is distinct from the exact membership given by [`Self::is_subset`].
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let a = Ipv4RTrieSet::from(["10.1.0.0/16".parse().unwrap()]);
+    /// let b = Ipv4RTrieSet::from(["10.0.0.0/8".parse().unwrap()]);
+    ///
+    /// assert!( a.is_covered_by(&b));
+    /// assert!( !b.is_covered_by(&a));
+    /// ```
+    #[inline]
+    pub fn is_covered_by(&self, other: &RTrieSet<P>) -> bool
+        where P: IpRootPrefix + IpPrefixFromSlot
+    {
+        self.address_difference(other).is_empty()
+    }
+
+    /// Checks if all the prefixes of this set are also present in the other one (exact match).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let a = Ipv4RTrieSet::from(["10.1.0.0/16".parse().unwrap()]);
+    /// let b = Ipv4RTrieSet::from(["10.0.0.0/8".parse().unwrap(), "10.1.0.0/16".parse().unwrap()]);
+    ///
+    /// assert!( a.is_subset(&b));
+    /// assert!( !b.is_subset(&a));
+    /// assert!( b.is_superset(&a));
+    /// ```
+    #[inline]
+    pub fn is_subset(&self, other: &RTrieSet<P>) -> bool
+        where P: IpPrefixCovering<P>
+    {
+        self.iter().all(|p| other.contains(p))
+    }
+
+    /// Checks if all the prefixes of the other set are also present in this one (exact match).
+    ///
+    /// See [`Self::is_subset`].
+    #[inline]
+    pub fn is_superset(&self, other: &RTrieSet<P>) -> bool
+        where P: IpPrefixCovering<P>
+    {
+        other.is_subset(self)
+    }
+
+    /// Gets the shortest prefix which covers all the prefixes of this set.
+    ///
+    /// The root prefix is not considered, except if the set is empty
+    /// (or if its prefixes span the whole address space).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from(["10.1.0.0/16".parse().unwrap(), "10.2.0.0/16".parse().unwrap()]);
+    /// assert_eq!( trie.bounding_prefix().to_string(), "10.0.0.0/14");
+    ///
+    /// let trie = Ipv4RTrieSet::from(["10.1.0.0/16".parse().unwrap(), "192.168.0.0/16".parse().unwrap()]);
+    /// assert_eq!( trie.bounding_prefix().to_string(), "0.0.0.0/0");
+    /// ```
+    pub fn bounding_prefix(&self) -> P
+        where P: IpRootPrefix + IpPrefixFromSlot
+    {
+        self.iter()
+            .skip(1) // skip the root prefix
+            .copied()
+            .reduce(|a, b| {
+                let len = (a.bitslot_trunc() ^ b.bitslot_trunc()).first_bit() - 1;
+                P::from_slot(a.bitslot_trunc(), len.min(a.len()).min(b.len()))
+            })
+            .unwrap_or(P::root())
+    }
+
+    /// Iterates over the prefixes of this set with their parent.
+    ///
+    /// The parent of a prefix is the longest other prefix of this set which covers it
+    /// (or `None` if the prefix is only covered by the root prefix, which is not iterated).
+    /// The prefixes are sorted by network address, then by length, so that a parent
+    /// always comes before its children.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from_iter(["10.1.1.0/24", "10.1.0.0/16", "11.0.0.0/8"].map(|p| p.parse().unwrap()));
+    ///
+    /// let tree = trie.iter_with_parent()
+    ///     .map(|(p, parent)| (p.to_string(), parent.map(|p| p.to_string())))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!( tree, [
+    ///     ("10.1.0.0/16".to_string(), None),
+    ///     ("10.1.1.0/24".to_string(), Some("10.1.0.0/16".to_string())),
+    ///     ("11.0.0.0/8".to_string(), None)]);
+    /// ```
+    pub fn iter_with_parent(&self) -> impl Iterator<Item=(&P, Option<&P>)> + '_
+    {
+        // the ancestors of a prefix are the last ones met at each lower depth
+        let mut ancestors = Vec::new();
+        self.0.hierarchy()
+            .into_iter()
+            .filter_map(move |(depth, l)| {
+                ancestors.truncate(depth);
+                let parent = ancestors.last().copied().filter(|a: &LeafIndex| !a.is_root_leaf()).map(|a| &self.0[a]);
+                ancestors.push(l);
+                (!l.is_root_leaf()).then(|| (&self.0[l], parent))
+            })
+    }
+
+    /// Gets a cursor positioned at the given prefix (exact match).
+    ///
+    /// Returns `None` if the prefix is not in the set.
+    /// The cursor could be positioned at the root prefix.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from_iter(["10.1.1.0/24", "10.1.0.0/16", "10.2.0.0/16"].map(|p| p.parse().unwrap()));
+    ///
+    /// let cursor = trie.cursor_at(&"10.1.0.0/16".parse::<Ipv4Prefix>().unwrap()).unwrap();
+    /// assert_eq!( cursor.first_child().unwrap().prefix().to_string(), "10.1.1.0/24");
+    /// assert_eq!( cursor.next_sibling().unwrap().prefix().to_string(), "10.2.0.0/16");
+    /// assert!( cursor.parent().unwrap().is_root() );
+    ///
+    /// assert!( trie.cursor_at(&"10.0.0.0/8".parse::<Ipv4Prefix>().unwrap()).is_none() );
+    /// ```
+    pub fn cursor_at<Q>(&self, k: &Q) -> Option<Cursor<'_,P>>
+        where
+            Q: IpPrefix<Addr=P::Addr>,
+            P: IpPrefixCovering<Q>
+    {
+        self.0.covering_leaves(k).next()
+            .filter(|&l| self.0[l].len() == k.len())
+            .map(|leaf| Cursor { set: self, leaf })
+    }
+
+    /// Iterates over the most specific prefixes of this set.
+    ///
+    /// A prefix is yielded if it does not cover any other prefix of this set
+    /// (the root prefix is never yielded). The prefixes are sorted by network address.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from(["10.0.0.0/8".parse().unwrap(), "10.0.0.0/24".parse().unwrap()]);
+    ///
+    /// assert_eq!( trie.maximal().map(|p| p.to_string()).collect::<Vec<_>>(), ["10.0.0.0/24"]);
+    /// ```
+    pub fn maximal(&self) -> impl Iterator<Item=&P> + '_
+    {
+        // in the sorted hierarchy, the descendants of a prefix follow it immediately
+        let hierarchy = self.0.hierarchy();
+        (0..hierarchy.len())
+            .filter_map(move |i| {
+                let (depth, l) = hierarchy[i];
+                let covers_none = !matches!(hierarchy.get(i+1), Some(&(next, _)) if next > depth);
+                (covers_none && !l.is_root_leaf()).then(|| &self.0[l])
+            })
+    }
+
+    /// Iterates over the least specific prefixes of this set.
+    ///
+    /// A prefix is yielded if it is not covered by any other prefix of this set
+    /// (except the root prefix, which is never yielded). The prefixes are sorted by network address.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from(["10.0.0.0/8".parse().unwrap(), "10.0.0.0/24".parse().unwrap()]);
+    ///
+    /// assert_eq!( trie.minimal().map(|p| p.to_string()).collect::<Vec<_>>(), ["10.0.0.0/8"]);
+    /// ```
+    pub fn minimal(&self) -> impl Iterator<Item=&P> + '_
+    {
+        // only covered by the root prefix
+        self.0.hierarchy()
+            .into_iter()
+            .filter(|&(depth, _)| depth == 1)
+            .map(|(_, l)| &self.0[l])
+    }
+
+    /// Splits this set into independent sets, one for each top-level prefix.
+    ///
+    /// Each prefix of [`Self::minimal`] is associated to the set of the prefixes
+    /// it covers (itself included). The root prefix is not considered.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from_iter(["10.0.0.0/8", "10.1.0.0/16", "11.0.0.0/8"].map(|p| p.parse().unwrap()));
+    ///
+    /// let parts = trie.partition_by_top();
+    /// assert_eq!( parts.len(), 2);
+    /// assert_eq!( parts[&"10.0.0.0/8".parse().unwrap()].len().get(), 3);
+    /// assert_eq!( parts[&"11.0.0.0/8".parse().unwrap()].len().get(), 2);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn partition_by_top(&self) -> std::collections::HashMap<P, RTrieSet<P>>
+        where P: IpRootPrefix + Eq + core::hash::Hash
+    {
+        let mut parts = std::collections::HashMap::new();
+        let mut top = None;
+        self.0.hierarchy()
+            .into_iter()
+            .filter(|&(depth, _)| depth > 0) // skip the root prefix
+            .for_each(|(depth, l)| {
+                if depth == 1 { top = Some(self.0[l]); }
+                // the hierarchy is sorted so the top prefix comes before the ones it covers
+                parts.entry(top.unwrap()).or_insert_with(RTrieSet::new).insert(self.0[l]);
+            });
+        parts
+    }
+
+    /// Removes all the prefixes which are covered by another prefix of this set.
+    ///
+    /// Only the shortest prefixes remain (with the root prefix).
+    /// Returns the number of removed prefixes.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut trie = Ipv4RTrieSet::from(["10.0.0.0/8".parse().unwrap(), "10.1.0.0/16".parse().unwrap(), "1.1.1.0/24".parse().unwrap()]);
+    /// assert_eq!( trie.dedup_covered(), 1);
+    /// assert_eq!( trie.iter().map(|p| p.to_string()).collect::<Vec<_>>(), ["0.0.0.0/0", "10.0.0.0/8", "1.1.1.0/24"]);
+    /// ```
+    pub fn dedup_covered(&mut self) -> usize
+    {
+        let covered = self.iter()
+            .skip(1) // skip the root prefix
+            .filter(|p| !self.0.covering_leaves(*p).nth(1).is_some_and(|l| l.is_root_leaf()))
+            .cloned()
+            .collect::<Vec<_>>();
+        covered.iter().for_each(|p| { self.remove(p); });
+        covered.len()
+    }
+
+    /// Removes all the prefixes covered by a prefix of the other set.
+    ///
+    /// The root prefix of the other set is not considered (and the root prefix
+    /// of this set is never removed).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut trie = Ipv4RTrieSet::from(["10.1.1.0/24".parse().unwrap(), "10.2.2.0/24".parse().unwrap(), "11.0.0.0/24".parse().unwrap()]);
+    /// let other = Ipv4RTrieSet::from(["10.0.0.0/8".parse().unwrap()]);
+    ///
+    /// trie.remove_all_covered_by(&other);
+    /// assert_eq!( trie.iter().map(|p| p.to_string()).collect::<Vec<_>>(), ["0.0.0.0/0", "11.0.0.0/24"]);
+    /// ```
+    pub fn remove_all_covered_by(&mut self, other: &RTrieSet<P>)
+    {
+        let covered = other.iter()
+            .skip(1) // skip the root prefix
+            .flat_map(|q| self.0.covered_leaves(q))
+            .filter(|l| !l.is_root_leaf())
+            .map(|l| self.0[l])
+            .collect::<Vec<_>>();
+        covered.iter().for_each(|p| { self.remove(p); });
+    }
+
+    /// Removes all the prefixes which are not covered by the given space.
+    ///
+    /// The root prefix is always kept. Only the path of the space is walked
+    /// in the trie: the prefixes of the subtrees which branch out of it are
+    /// removed without testing their coverage one by one.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut trie = Ipv4RTrieSet::from(["10.1.1.0/24".parse().unwrap(), "192.168.1.0/24".parse().unwrap(), "10.2.0.0/16".parse().unwrap()]);
+    ///
+    /// trie.restrict_to("10.0.0.0/8".parse().unwrap());
+    /// let mut kept = trie.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+    /// kept.sort();
+    /// assert_eq!( kept, ["0.0.0.0/0", "10.1.1.0/24", "10.2.0.0/16"]);
+    /// ```
+    pub fn restrict_to(&mut self, space: P)
+    {
+        // collect the prefixes first since each removal moves a leaf
+        let outside = self.0.uncovered_leaves(&space)
+            .into_iter()
+            .map(|l| self.0[l])
+            .collect::<Vec<_>>();
+        outside.iter().for_each(|p| { self.remove(p); });
+    }
+
+    /// Builds the set of the prefixes of the given length which cover the same addresses.
+    ///
+    /// Each prefix shorter than `len` is replaced by all its subnets of length `len`
+    /// and each longer prefix is shortened to `len`. The root prefix is not considered.
+    ///
+    /// **Warning:** the size of the result grows exponentially with the difference
+    /// between `len` and the length of the stored prefixes (exploding a `/8` to
+    /// `/24` gives 65536 prefixes).
+    ///
+    /// # Panics
+    /// Panics if `len` is greater than the maximal length of the prefixes.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from(["10.0.0.0/22".parse().unwrap(), "10.1.1.128/25".parse().unwrap()]);
+    ///
+    /// let exploded = trie.explode_to(24);
+    /// assert_eq!( exploded.len().get(), 6);
+    /// assert!( exploded.contains(&"10.0.3.0/24".parse::<Ipv4Prefix>().unwrap()));
+    /// assert!( exploded.contains(&"10.1.1.0/24".parse::<Ipv4Prefix>().unwrap()));
+    /// ```
+    pub fn explode_to(&self, len: u8) -> RTrieSet<P>
+        where P: IpRootPrefix + IpPrefixFromSlot
+    {
+        assert!( len <= P::MAX_LEN, "prefix length too long");
+        let mut exploded = RTrieSet::new();
+        let mut stack = self.iter()
+            .skip(1) // skip the root prefix
+            .map(|p| P::from_slot(p.bitslot_trunc(), p.len().min(len)))
+            .collect::<Vec<_>>();
+        while let Some(p) = stack.pop() {
+            match p.split() {
+                Some((a,b)) if p.len() < len => { stack.push(b); stack.push(a); }
+                _ => { exploded.insert(p); }
+            }
+        }
+        exploded
+    }
+
+    // the prefixes which are only covered by the root prefix
+    fn top_prefixes(&self) -> impl Iterator<Item=&P> + '_ {
+        self.iter()
+            .skip(1) // skip the root prefix
+            .filter(|p| self.0.covering_leaves(*p).nth(1).is_some_and(|l| l.is_root_leaf()))
+    }
+
+    /// Counts the addresses covered by the prefixes of this set.
+    ///
+    /// The root prefix is not considered and an address covered by many
+    /// prefixes is counted once. The result saturates at `u128::MAX`
+    /// (see [`Self::address_count_checked`]).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from(["10.0.0.0/8".parse().unwrap(), "10.1.0.0/16".parse().unwrap(), "1.1.1.0/24".parse().unwrap()]);
+    /// assert_eq!( trie.address_count(), (1 << 24) + 256);
+    /// ```
+    #[inline]
+    pub fn address_count(&self) -> u128
+        where P: Into<IpNet>
+    {
+        self.top_prefixes()
+            .map(|p| Self::prefix_address_count(*p).unwrap_or(u128::MAX))
+            .fold(0, u128::saturating_add)
+    }
+
+    /// Counts the addresses covered by the prefixes of this set.
+    ///
+    /// Same as [`Self::address_count`] but returns `None` if the result
+    /// overflows a `u128`, which occurs for Ipv6 when the whole address space is covered.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut trie = Ipv6RTrieSet::from(["::/1".parse().unwrap()]);
+    /// assert_eq!( trie.address_count_checked(), Some(1 << 127));
+    ///
+    /// trie.insert("8000::/1".parse().unwrap());
+    /// assert_eq!( trie.address_count_checked(), None);
+    /// assert_eq!( trie.address_count(), u128::MAX);
+    /// ```
+    pub fn address_count_checked(&self) -> Option<u128>
+        where P: Into<IpNet>
+    {
+        self.top_prefixes()
+            .try_fold(0u128, |n, p| n.checked_add(Self::prefix_address_count(*p)?))
+    }
+
+    /// Approximates the number of addresses covered by the prefixes of this set.
+    ///
+    /// Same as [`Self::address_count`] but never overflows.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv6RTrieSet::from(["::/1".parse().unwrap(), "8000::/1".parse().unwrap()]);
+    /// assert_eq!( trie.address_count_f64(), 2f64.powi(128));
+    /// ```
+    pub fn address_count_f64(&self) -> f64
+        where P: Into<IpNet>
+    {
+        self.top_prefixes()
+            .map(|p| Self::prefix_address_count_f64(*p))
+            .sum()
+    }
+
+    /// Computes the fraction of the addresses of a space which are covered by this set.
+    ///
+    /// The root prefix is not considered and an address covered by many
+    /// prefixes is counted once.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from(["10.0.0.0/9".parse().unwrap(), "10.192.0.0/10".parse().unwrap()]);
+    ///
+    /// assert_eq!( trie.coverage_ratio("10.0.0.0/8".parse().unwrap()), 0.75);
+    /// assert_eq!( trie.coverage_ratio("10.0.0.0/16".parse().unwrap()), 1.);
+    /// assert_eq!( trie.coverage_ratio("11.0.0.0/8".parse().unwrap()), 0.);
+    /// ```
+    pub fn coverage_ratio(&self, space: P) -> f64
+        where P: Into<IpNet> + IpPrefixCovering<P>
+    {
+        if self.nearest(&space).is_some() {
+            return 1.; // fully covered by a stored prefix
+        }
+        // so the prefixes within the space are only covered by the root outside of it
+        let covered = self.0.covered_leaves(&space)
+            .into_iter()
+            .filter(|l| !l.is_root_leaf())
+            .filter(|&l| self.0.covering_leaves(&self.0[l]).nth(1).is_some_and(|l| l.is_root_leaf()))
+            .map(|l| Self::prefix_address_count_f64(self.0[l]))
+            .sum::<f64>();
+        covered / Self::prefix_address_count_f64(space)
+    }
+
+    // approximated number of addresses of a prefix
+    #[inline]
+    fn prefix_address_count_f64(p: P) -> f64
+        where P: Into<IpNet>
+    {
+        // only an Ipv6 /0 prefix could overflow (2^128 addresses)
+        Self::prefix_address_count(p).map_or(2. * (1u128 << 127) as f64, |n| n as f64)
+    }
+
+    // number of addresses of a prefix or None if greater than u128::MAX
+    #[inline]
+    fn prefix_address_count(p: P) -> Option<u128>
+        where P: Into<IpNet>
+    {
+        let net: IpNet = p.into();
+        1u128.checked_shl((net.max_prefix_len() - net.prefix_len()) as u32)
+    }
+
+    /// Writes the hierarchy of the prefixes of this set as an indented text tree.
+    ///
+    /// Each prefix is written on its own line (sorted by address) and is indented
+    /// according to the number of prefixes which cover it. It is a lightweight
+    /// alternative to the graphviz output for debugging.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from(["10.0.0.0/8".parse().unwrap(), "10.1.0.0/16".parse().unwrap(), "1.1.1.0/24".parse().unwrap()]);
+    ///
+    /// let mut out = Vec::new();
+    /// trie.write_tree(&mut out).unwrap();
+    /// assert_eq!( String::from_utf8(out).unwrap(), "0.0.0.0/0\n  1.1.1.0/24\n  10.0.0.0/8\n    10.1.0.0/16\n");
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_tree<W: std::io::Write>(&self, out: &mut W) -> std::io::Result<()>
+        where P: fmt::Display
+    {
+        self.0.hierarchy()
+            .into_iter()
+            .try_for_each(|(depth, l)| writeln!(out, "{:indent$}{}", "", self.0[l], indent = 2*depth))
+    }
+
+    /// Writes all the prefixes of this set, one per line.
+    ///
+    /// The root prefix is not written. Depending on the format, each prefix
+    /// is written as a CIDR (`10.0.0.0/8`) or as a range of addresses
+    /// (`10.0.0.0-10.255.255.255`), as expected by tools like nftables or ipset.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from(["10.0.0.0/8".parse().unwrap(), "1.1.1.0/24".parse().unwrap()]);
+    ///
+    /// let mut out = Vec::new();
+    /// trie.export(&mut out, ExportFormat::Cidr).unwrap();
+    /// assert_eq!( String::from_utf8(out).unwrap(), "10.0.0.0/8\n1.1.1.0/24\n");
+    ///
+    /// let mut out = Vec::new();
+    /// trie.export(&mut out, ExportFormat::Range).unwrap();
+    /// assert_eq!( String::from_utf8(out).unwrap(), "10.0.0.0-10.255.255.255\n1.1.1.0-1.1.1.255\n");
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn export<W: std::io::Write>(&self, out: &mut W, fmt: ExportFormat) -> std::io::Result<()>
+        where P: Into<IpNet>
+    {
+        self.iter_ipnet()
+            .skip(1) // skip the root prefix
+            .try_for_each(|net| match fmt {
+                ExportFormat::Cidr => writeln!(out, "{}", net.trunc()),
+                ExportFormat::Range => writeln!(out, "{}-{}", net.network(), net.broadcast()),
+            })
+    }
+
+    /// Writes all the prefixes of this set as a JSON array of strings.
+    ///
+    /// The root prefix is not written. The output could be read back
+    /// by [`Self::from_json`].
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from(["10.0.0.0/8".parse().unwrap(), "1.1.1.0/24".parse().unwrap()]);
+    ///
+    /// let mut out = Vec::new();
+    /// trie.write_json(&mut out).unwrap();
+    /// assert_eq!( String::from_utf8(out).unwrap(), r#"["10.0.0.0/8", "1.1.1.0/24"]"#);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_json<W: std::io::Write>(&self, out: &mut W) -> std::io::Result<()>
+        where P: fmt::Display
+    {
+        out.write_all(b"[")?;
+        self.iter_non_root()
+            .enumerate()
+            .try_for_each(|(i, p)| write!(out, "{}\"{}\"", if i == 0 { "" } else { ", " }, p))?;
+        out.write_all(b"]")
+    }
+
+    /// Checks the consistency of the underlying trie structure.
+    ///
+    /// This is only a debugging and fuzzing helper (not part of the API):
+    /// a corrupted trie is reported by an error message instead of a silent misbehaviour.
+    #[doc(hidden)]
+    #[cfg(any(test, debug_assertions))]
+    #[inline]
+    pub fn check_invariants(&self) -> Result<(), String> { self.0.check_invariants() }
+
+    /// Gets the length of the longest run of consecutive branching nodes
+    /// which share the same escape prefix.
+    ///
+    /// When a lookup does not find a matching leaf, it climbs the trie
+    /// through such a run to get the escape prefix, so a long run
+    /// reveals a pathological set of prefixes (many prefixes under a common one).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from_iter(["1.0.0.0/8", "2.0.0.0/8"].map(|p| p.parse().unwrap()));
+    /// assert_eq!( trie.max_escape_chain(), 2);
+    /// ```
+    #[inline]
+    pub fn max_escape_chain(&self) -> usize { self.0.max_escape_chain() }
+
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn info(&self) { self.0.info() }
+}
+
+impl RTrieSet<Ipv4Prefix>
+{
+    /// Builds a set from a list of addresses with their prefix length.
+    ///
+    /// The host bits are cleared (see [`Ipv4Prefix::new`]). The building stops
+    /// at the first invalid length and the error is returned.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv4Addr;
+    /// let trie = Ipv4RTrieSet::from_addr_len_iter([(Ipv4Addr::new(10,0,0,0), 8), (Ipv4Addr::new(10,1,1,1), 16)]).unwrap();
+    /// assert_eq!( trie.len().get(), 3);
+    ///
+    /// let err = Ipv4RTrieSet::from_addr_len_iter([(Ipv4Addr::new(10,0,0,0), 33)]);
+    /// assert_eq!( err.err(), Some(IpPrefixError::PrefixLenError));
+    /// ```
+    pub fn from_addr_len_iter<I: IntoIterator<Item=(Ipv4Addr, u8)>>(iter: I) -> Result<Self, IpPrefixError>
+    {
+        let mut trie = Self::new();
+        for (addr, len) in iter {
+            trie.insert(Ipv4Prefix::new(addr, len)?);
+        }
+        Ok(trie)
+    }
+
+    /// Converts this set in a set of [`Ipv4Net`].
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use ipnet::Ipv4Net;
+    /// let trie = Ipv4RTrieSet::from(["1.1.0.0/16".parse().unwrap()]);
+    /// let nets: RTrieSet<Ipv4Net> = trie.into_net();
+    /// assert_eq!( nets.iter().map(|p| p.to_string()).collect::<Vec<_>>(), ["0.0.0.0/0", "1.1.0.0/16"]);
+    /// ```
+    #[inline]
+    pub fn into_net(self) -> RTrieSet<Ipv4Net> {
+        self.iter().map(|p| Ipv4Net::from(*p)).collect()
+    }
+
+    /// Inserts a [`Ipv4Net`] in the set.
+    ///
+    /// Same as [`Self::insert`] with a prior conversion of the network.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use ipnet::Ipv4Net;
+    /// let mut trie = Ipv4RTrieSet::new();
+    /// let net = "1.1.0.0/16".parse::<Ipv4Net>().unwrap();
+    ///
+    /// assert_eq!( trie.insert_net(net), true);
+    /// assert_eq!( trie.insert_net(net), false);
+    /// assert!( trie.contains(&"1.1.0.0/16".parse::<Ipv4Prefix>().unwrap()));
+    /// ```
+    #[inline]
+    pub fn insert_net(&mut self, net: Ipv4Net) -> bool {
+        self.insert(net.into())
+    }
+
+    /// Checks if a [`Ipv4Net`] is present (exact match).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use ipnet::Ipv4Net;
+    /// let trie = Ipv4RTrieSet::from(["1.1.0.0/16".parse().unwrap()]);
+    ///
+    /// assert!( trie.contains_net("1.1.0.0/16".parse::<Ipv4Net>().unwrap()));
+    /// assert!( !trie.contains_net("1.2.0.0/16".parse::<Ipv4Net>().unwrap()));
+    /// ```
+    #[inline]
+    pub fn contains_net(&self, net: Ipv4Net) -> bool {
+        self.contains(&Ipv4Prefix::from(net))
+    }
+
+    /// Removes a [`Ipv4Net`] from the set (exact match).
+    ///
+    /// Returns `false` is the network was not present in the set.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use ipnet::Ipv4Net;
+    /// let mut trie = Ipv4RTrieSet::from(["1.1.0.0/16".parse().unwrap()]);
+    /// let net = "1.1.0.0/16".parse::<Ipv4Net>().unwrap();
+    ///
+    /// assert_eq!( trie.remove_net(net), true);
+    /// assert_eq!( trie.remove_net(net), false);
+    /// ```
+    #[inline]
+    pub fn remove_net(&mut self, net: Ipv4Net) -> bool {
+        self.remove(&Ipv4Prefix::from(net))
+    }
+}
+
+impl RTrieSet<Ipv6Prefix>
+{
+    /// Builds a set from a list of addresses with their prefix length.
+    ///
+    /// The host bits are cleared (see [`Ipv6Prefix::new`]). The building stops
+    /// at the first invalid length and the error is returned.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv6Addr;
+    /// let trie = Ipv6RTrieSet::from_addr_len_iter([(Ipv6Addr::new(0x2001,0xdb8,0,0,0,0,0,0), 32)]).unwrap();
+    /// assert_eq!( trie.len().get(), 2);
+    ///
+    /// let err = Ipv6RTrieSet::from_addr_len_iter([(Ipv6Addr::LOCALHOST, 129)]);
+    /// assert_eq!( err.err(), Some(IpPrefixError::PrefixLenError));
+    /// ```
+    pub fn from_addr_len_iter<I: IntoIterator<Item=(Ipv6Addr, u8)>>(iter: I) -> Result<Self, IpPrefixError>
+    {
+        let mut trie = Self::new();
+        for (addr, len) in iter {
+            trie.insert(Ipv6Prefix::new(addr, len)?);
+        }
+        Ok(trie)
+    }
+
+    /// Converts this set in a set of [`Ipv6Net`].
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use ipnet::Ipv6Net;
+    /// let trie = Ipv6RTrieSet::from(["1:1::/32".parse().unwrap()]);
+    /// let nets: RTrieSet<Ipv6Net> = trie.into_net();
+    /// assert_eq!( nets.iter().map(|p| p.to_string()).collect::<Vec<_>>(), ["::/0", "1:1::/32"]);
+    /// ```
+    #[inline]
+    pub fn into_net(self) -> RTrieSet<Ipv6Net> {
+        self.iter().map(|p| Ipv6Net::from(*p)).collect()
+    }
+
+    /// Inserts a [`Ipv6Net`] in the set.
+    ///
+    /// Same as [`Self::insert`] with a prior conversion of the network.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use ipnet::Ipv6Net;
+    /// let mut trie = Ipv6RTrieSet::new();
+    /// let net = "1:1::/32".parse::<Ipv6Net>().unwrap();
+    ///
+    /// assert_eq!( trie.insert_net(net), true);
+    /// assert_eq!( trie.insert_net(net), false);
+    /// assert!( trie.contains(&"1:1::/32".parse::<Ipv6Prefix>().unwrap()));
+    /// ```
+    #[inline]
+    pub fn insert_net(&mut self, net: Ipv6Net) -> bool {
+        self.insert(net.into())
+    }
+
+    /// Checks if a [`Ipv6Net`] is present (exact match).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use ipnet::Ipv6Net;
+    /// let trie = Ipv6RTrieSet::from(["1:1::/32".parse().unwrap()]);
+    ///
+    /// assert!( trie.contains_net("1:1::/32".parse::<Ipv6Net>().unwrap()));
+    /// assert!( !trie.contains_net("1:2::/32".parse::<Ipv6Net>().unwrap()));
+    /// ```
+    #[inline]
+    pub fn contains_net(&self, net: Ipv6Net) -> bool {
+        self.contains(&Ipv6Prefix::from(net))
+    }
+
+    /// Removes a [`Ipv6Net`] from the set (exact match).
+    ///
+    /// Returns `false` is the network was not present in the set.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use ipnet::Ipv6Net;
+    /// let mut trie = Ipv6RTrieSet::from(["1:1::/32".parse().unwrap()]);
+    /// let net = "1:1::/32".parse::<Ipv6Net>().unwrap();
+    ///
+    /// assert_eq!( trie.remove_net(net), true);
+    /// assert_eq!( trie.remove_net(net), false);
+    /// ```
+    #[inline]
+    pub fn remove_net(&mut self, net: Ipv6Net) -> bool {
+        self.remove(&Ipv6Prefix::from(net))
+    }
+}
+
+impl<P:IpRootPrefix> Default for RTrieSet<P>
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a,P:IpPrefix> IntoIterator for &'a RTrieSet<P>
+{
+    type Item = &'a P;
+    type IntoIter = SetIter<'a,P>;
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter { self.iter() }
+}
+
+impl<'a,P:IpPrefix> IntoIterator for &'a LCTrieSet<P>
+{
+    type Item = &'a P;
+    type IntoIter = SetIter<'a,P>;
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter { self.iter() }
+}
+
+impl<P:IpPrefix> Extend<P> for RTrieSet<P>
+{
+    fn extend<I: IntoIterator<Item=P>>(&mut self, iter: I)
+    {
+        iter.into_iter().for_each(| item | { self.insert(item); } )
+    }
+}
+
+impl<P:IpRootPrefix> FromIterator<P> for RTrieSet<P>
+{
+    fn from_iter<I:IntoIterator<Item=P>>(iter: I) -> Self
+    {
+        let mut trieset = Self::default();
+        trieset.extend(iter);
+        trieset
+    }
+}
+
+impl<P:IpRootPrefix, const N: usize> From<[P; N]> for RTrieSet<P>
+{
+    /// Builds a set from an array of prefixes.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from(["1.0.0.0/8".parse().unwrap()]);
+    /// assert!( trie.contains(&"1.0.0.0/8".parse::<Ipv4Prefix>().unwrap()));
+    /// ```
+    #[inline]
+    fn from(array: [P; N]) -> Self { Self::from_iter(array) }
+}
+
+impl<P:IpRootPrefix> From<Vec<P>> for RTrieSet<P>
+{
+    #[inline]
+    fn from(vec: Vec<P>) -> Self { Self::from_iter(vec) }
+}
+
+impl<P:IpPrefix> LCTrieSet<P>
+{
+    /// Returns the size of the set.
+    ///
+    /// Notice that it never equals zero since the top prefix is
+    /// always present in the set.
+    #[inline]
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> NonZeroUsize { self.0.len() }
+
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn info(&self) { self.0.info() }
+
+    /// Reports how the branching nodes were merged by the compression.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from_iter(["1.0.0.0/8", "2.0.0.0/8", "3.0.0.0/8"].map(|p| p.parse().unwrap()));
+    /// let report = trie.compress().compression_report();
+    ///
+    /// assert_eq!( report.patricia_nodes, 3);
+    /// assert_eq!( report.nodes, 2);
+    /// assert_eq!( report.sizes[1..3], [1, 1]);
+    /// ```
+    #[inline]
+    pub fn compression_report(&self) -> CompressionReport { self.0.compression_report() }
+
+    /// Memory size (in bytes) used by the nodes and the leaves of this trie.
+    ///
+    /// The size of the data referred by the values (if any) is not counted.
+    #[inline]
+    pub fn memory_bytes(&self) -> usize { self.0.memory_bytes() }
+
+    /// Compresses a Patricia trie in a memory bounded to the given number of bytes.
+    ///
+    /// The memory is shared by the compressed nodes and the leaves. The leaves are
+    /// kept as allocated by the Patricia trie and their whole capacity is counted,
+    /// so, once the Patricia trie is shrunk (see [`RTrieSet::shrink_to_fit`]),
+    /// the required size is given by [`RTrieSet::estimated_compressed_bytes`].
+    /// The compressed nodes are allocated once with all the memory which is
+    /// not used by the leaves and they never exceed it nor reallocate.
+    /// If the memory is too small, an error is returned before any allocation
+    /// (and the Patricia trie is lost).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let mut trie = Ipv4RTrieSet::from_iter(["1.1.0.0/16", "1.1.1.0/24", "2.0.0.0/8"].map(|p| p.parse().unwrap()));
+    /// trie.shrink_to_fit();
+    /// let needed = trie.estimated_compressed_bytes();
+    ///
+    /// assert_eq!( LCTrieSet::build_with_arena_capacity(trie.clone(), needed - 1).err(), Some(CapacityError));
+    ///
+    /// let lctrie = LCTrieSet::build_with_arena_capacity(trie, needed).unwrap();
+    /// assert_eq!( lctrie.memory_bytes(), needed);
+    /// ```
+    #[inline]
+    pub fn build_with_arena_capacity(rtrie: RTrieSet<P>, arena_bytes: usize) -> Result<Self, CapacityError> {
+        LevelCompressedTrie::with_arena_capacity(rtrie.0, arena_bytes).map(Self)
+    }
+
+    /// Checks if an element is present (exact match).
     ///
     /// # Example
     /// ```
@@ -391,10 +2099,153 @@ impl<P:IpPrefix> LCTrieSet<P>
         self.0.lookup(k).0
     }
 
+    /// Gets the longest prefix which matches the given key, except the root prefix.
+    ///
+    /// Same as [`Self::lookup`] but returns `None` if the only matching prefix
+    /// is the root one.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv4Addr;
+    /// let trie = Ipv4RTrieSet::from(["1.1.1.0/24".parse().unwrap()]).compress();
+    ///
+    /// assert_eq!( trie.nearest(&Ipv4Addr::new(1,1,1,1)).map(|p| p.to_string()), Some("1.1.1.0/24".to_string()));
+    /// assert_eq!( trie.nearest(&Ipv4Addr::new(2,2,2,2)), None);
+    /// ```
+    #[inline]
+    pub fn nearest<Q>(&self, k: &Q) -> Option<&P>
+        where
+            Q: IpPrefix<Addr=P::Addr>,
+            P: IpPrefixCovering<Q>
+    {
+        Some(self.lookup(k)).filter(|p| p.len() > 0)
+    }
+
+    /// Gets a copy of the longest prefix which matches the given key.
+    ///
+    /// Same as [`Self::lookup`] but the result does not borrow the set.
+    #[inline]
+    pub fn lookup_owned<Q>(&self, k: &Q) -> P
+        where
+            Q: IpPrefix<Addr=P::Addr>,
+            P: IpPrefixCovering<Q>
+    {
+        *self.lookup(k)
+    }
+
+    /// Gets the longest prefix which matches the given key and checks if it is not the root one.
+    ///
+    /// The boolean is `true` if a prefix other than the root one matches.
+    #[inline]
+    pub fn lookup_checked<Q>(&self, k: &Q) -> (&P, bool)
+        where
+            Q: IpPrefix<Addr=P::Addr>,
+            P: IpPrefixCovering<Q>
+    {
+        let found = self.lookup(k);
+        (found, found.len() > 0)
+    }
+
+    /// Gets a token of the longest prefix which matches the given key.
+    ///
+    /// The token identifies the matching prefix inside this set, so it could be used
+    /// to key external data (see [`LookupToken::index`]) and the prefix could be
+    /// accessed again without any lookup (see [`LookupToken::resolve`]).
+    ///
+    /// The tokens are only meaningful for this set: they are invalidated
+    /// by a recompression (even of the same prefixes).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv4Addr;
+    /// let lctrie = Ipv4LCTrieSet::from_iter(["1.1.0.0/16".parse().unwrap(), "1.1.1.0/24".parse().unwrap()]);
+    ///
+    /// let t1 = lctrie.lookup_token(&Ipv4Addr::new(1,1,1,1));
+    /// let t2 = lctrie.lookup_token(&Ipv4Addr::new(1,1,1,2));
+    /// assert_eq!( t1, t2);
+    /// assert_eq!( t1.resolve().to_string(), "1.1.1.0/24");
+    /// assert_ne!( t1, lctrie.lookup_token(&Ipv4Addr::new(1,1,2,1)));
+    /// ```
+    #[inline]
+    pub fn lookup_token<Q>(&self, k: &Q) -> LookupToken<'_,P>
+        where
+            Q: IpPrefix<Addr=P::Addr>,
+            P: IpPrefixCovering<Q>
+    {
+        LookupToken { set: self, leaf: self.0.inner_lookup(k) }
+    }
+
+    /// Gets the longest prefix match of each of the given addresses.
+    ///
+    /// This is the entry point for batch lookups: the results are
+    /// the same as calling [`Self::lookup`] for each address.
+    /// No allocation is performed.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv4Addr;
+    /// let trie = Ipv4RTrieSet::from(["1.1.0.0/16".parse().unwrap(), "1.1.1.0/24".parse().unwrap()]);
+    /// let lctrie = trie.compress();
+    ///
+    /// let addrs = [Ipv4Addr::new(1,1,1,1), Ipv4Addr::new(1,1,2,1), Ipv4Addr::new(2,1,1,1)];
+    /// assert!( lctrie.lookup_many(addrs.into_iter()).eq(addrs.iter().map(|a| lctrie.lookup(a))));
+    /// ```
+    #[inline]
+    pub fn lookup_many<'a, I>(&'a self, addrs: I) -> impl Iterator<Item=&'a P> + 'a
+        where
+            I: Iterator<Item=P::Addr> + 'a,
+            P::Addr: IpPrefix<Addr=P::Addr>,
+            P: IpPrefixCovering<P::Addr>
+    {
+        addrs.map(move |addr| self.0.lookup(&addr).0)
+    }
+
     /// Iterates over all the prefixes of this set.
+    ///
+    /// The order is the one of [`RTrieSet::iter_insertion_order`] for the set
+    /// which was compressed (it is kept as is by the compression).
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// let trie = Ipv4RTrieSet::from(["1.1.1.0/24".parse().unwrap(), "1.0.0.0/8".parse().unwrap(), "1.1.0.0/16".parse().unwrap()]);
+    /// let lctrie = trie.clone().compress();
+    /// assert!( lctrie.iter().eq(trie.iter_insertion_order()));
+    /// ```
     #[inline]
-    pub fn iter(&self) -> impl Iterator<Item=&P> + '_ {
-        self.0.leaves.0.iter().map(Leaf::prefix)
+    pub fn iter(&self) -> SetIter<'_,P> {
+        SetIter(self.0.leaves.0.iter())
+    }
+}
+
+impl<P:IpPrefix> Extend<P> for LCTrieSet<P>
+{
+    /// Extends the compressed set with the contents of an iterator.
+    ///
+    /// The set is rebuilt as a Patricia trie, the items are inserted
+    /// and the result is compressed again. So each call is O(n): it is
+    /// intended for rare batch updates, not for per-item insertions.
+    ///
+    /// # Example
+    /// ```
+    /// # use iptrie::*;
+    /// use std::net::Ipv4Addr;
+    /// let mut lctrie = Ipv4LCTrieSet::from_iter(["1.1.0.0/16".parse().unwrap()]);
+    /// lctrie.extend(["1.1.1.0/24".parse().unwrap(), "2.0.0.0/8".parse().unwrap()]);
+    ///
+    /// assert_eq!( lctrie.len().get(), 4);
+    /// assert_eq!( lctrie.lookup(&Ipv4Addr::new(1,1,1,1)).to_string(), "1.1.1.0/24");
+    /// assert_eq!( lctrie.lookup(&Ipv4Addr::new(1,1,2,1)).to_string(), "1.1.0.0/16");
+    /// assert_eq!( lctrie.lookup(&Ipv4Addr::new(2,1,1,1)).to_string(), "2.0.0.0/8");
+    /// ```
+    fn extend<I: IntoIterator<Item=P>>(&mut self, iter: I)
+    {
+        let mut trie = RTrieSet(self.0.decompress());
+        trie.extend(iter);
+        *self = trie.compress();
     }
 }
 
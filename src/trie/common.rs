@@ -1,9 +1,18 @@
-use std::{fmt, iter, vec};
-use std::ops::{Index, IndexMut};
-use crate::prefix::IpPrefix;
+use core::{fmt, iter};
+use core::cmp::Ordering;
+use alloc::vec;
+use core::ops::{Index, IndexMut};
+use crate::prefix::{BitSlot, IpPrefix};
+use super::storage::{HeapStorage, NodeVec, TrieStorage};
 
-#[derive(Clone)]
-pub(crate) struct TrieLeaves<L>(pub(crate) Vec<L>);
+pub(crate) struct TrieLeaves<L, S: TrieStorage = HeapStorage>(pub(crate) S::Vec<L>);
+
+impl<L, S: TrieStorage> Clone for TrieLeaves<L,S>
+    where S::Vec<L>: Clone
+{
+    #[inline]
+    fn clone(&self) -> Self { Self(self.0.clone()) }
+}
 
 
 #[derive(Clone)]
@@ -22,19 +31,20 @@ impl<K,V> From<Leaf<K,V>> for (K,V) {
     }
 }
 
-impl<K: IpPrefix, V> TrieLeaves<Leaf<K, V>>
+impl<K: IpPrefix, V, S: TrieStorage> TrieLeaves<Leaf<K, V>, S>
 {
     pub fn new(capacity: usize, k:K, v:V) -> Self {
-        let mut leaves = Vec::with_capacity(capacity);
+        let mut leaves = S::Vec::with_capacity(capacity);
         leaves.push(Leaf::new(k,v));
         Self(leaves)
     }
 }
 
-impl<L> TrieLeaves<L>
+impl<L, S: TrieStorage> TrieLeaves<L,S>
 {
     // returns the index of the added leaf
     pub fn push(&mut self, leaf: L) -> LeafIndex {
+        assert!( check_index(self.0.len()).is_ok(), "too many leaves in the trie");
         let index = self.0.len().into();
         self.0.push(leaf);
         index
@@ -57,7 +67,7 @@ impl<L> TrieLeaves<L>
 }
 
 
-impl<L> Index<LeafIndex> for TrieLeaves<L>
+impl<L, S: TrieStorage> Index<LeafIndex> for TrieLeaves<L,S>
 {
     type Output = L;
 
@@ -68,7 +78,7 @@ impl<L> Index<LeafIndex> for TrieLeaves<L>
     }
 }
 
-impl<L> IndexMut<LeafIndex> for TrieLeaves<L>
+impl<L, S: TrieStorage> IndexMut<LeafIndex> for TrieLeaves<L,S>
 {
     fn index_mut(&mut self, i: LeafIndex) -> &mut Self::Output
     {
@@ -91,8 +101,44 @@ impl<K,V> IntoIterator for TrieLeaves<Leaf<K,V>>
 
 
 
+/// Compares two prefixes by network address, then by length
+pub(crate) fn cmp_prefixes<K: IpPrefix, Q: IpPrefix<Slot=K::Slot>>(a: &K, b: &Q) -> Ordering
+{
+    let diff = a.bitslot_trunc() ^ b.bitslot_trunc();
+    if diff == K::Slot::default() {
+        a.len().cmp(&b.len())
+    } else if b.bitslot_trunc().is_set(diff.first_bit()) {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    }
+}
+
+
+/// Error generated when inserting in a full [`crate::ArrayRTrieSet`]
+/// or in a trie which has reached its maximal number of nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityError;
+
+impl fmt::Display for CapacityError
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("trie capacity exhausted")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CapacityError {}
+
 pub static INDEX_MAX:usize = i32::MAX as usize;
 
+/// Checks that a new node could be stored at the given index
+#[inline]
+pub(crate) fn check_index(i: usize) -> Result<(), CapacityError>
+{
+    if i <= INDEX_MAX { Ok(()) } else { Err(CapacityError) }
+}
+
 /// index of any node (leaf or branching, work as union)
 #[derive(Copy, Clone, Eq, PartialEq, Hash)]
 pub(crate) struct NodeIndex(pub(crate) i32);
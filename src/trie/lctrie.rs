@@ -1,7 +1,9 @@
-use std::fmt;
-use std::mem::size_of;
-use std::num::NonZeroUsize;
-use std::ops::{Index, IndexMut};
+use core::fmt;
+use core::mem::size_of;
+use core::num::NonZeroUsize;
+use core::ops::{Index, IndexMut};
+use alloc::vec;
+use alloc::vec::Vec;
 
 use super::patricia::*;
 
@@ -9,8 +11,10 @@ use crate::prefix::*;
 use crate::trie::common::*;
 
 pub(crate) struct LevelCompressedTrie<K,V> {
-    branching: CompressedTree,
-    pub(crate) leaves: TrieLeaves<Leaf<K,V>>
+    pub(crate) branching: CompressedTree,
+    pub(crate) leaves: TrieLeaves<Leaf<K,V>>,
+    // number of branching of the Patricia trie before compression
+    patricia: usize
 }
 
 impl<K,V> LevelCompressedTrie<K,V>
@@ -25,25 +29,91 @@ impl<K,V> LevelCompressedTrie<K,V>
 }
 
 impl<K:IpPrefix,V>  LevelCompressedTrie<K,V> {
+    #[inline]
     pub(crate) fn new(trie: RadixTrie<K, V>) -> Self
+    {
+        let capacity = trie.branching.0.len();
+        Self::with_capacity(trie, capacity)
+    }
+
+    /// Compresses the trie with an initial capacity given in number of branching
+    ///
+    /// The capacity is only a hint: the compressed tree grows as needed.
+    pub(crate) fn with_capacity(trie: RadixTrie<K, V>, capacity: usize) -> Self
+    {
+        let sizes = compressed_sizes(&trie.branching, 0);
+        let Ok(mut lctrie) = Self::compress_into(trie, &sizes, CompressedTree::with_capacity(capacity))
+            else { unreachable!("an unbounded compressed tree never fails") };
+        lctrie.branching.memzone.shrink_to_fit();
+        lctrie.leaves.0.shrink_to_fit();
+        lctrie
+    }
+
+    /// Compresses the trie in a preallocated memory (the compressed nodes and the leaves)
+    ///
+    /// The leaves are kept as allocated by the Patricia trie: their whole capacity
+    /// is counted. Fails, before any allocation, if the memory is too small.
+    /// Otherwise, the compressed nodes are stored in a memzone allocated once with
+    /// the remaining memory, which is never exceeded nor reallocated.
+    pub(crate) fn with_arena_capacity(trie: RadixTrie<K, V>, arena_bytes: usize) -> Result<Self, CapacityError>
+    {
+        let sizes = compressed_sizes(&trie.branching, 0);
+        let leaves = trie.leaves.0.capacity() * size_of::<Leaf<K,V>>();
+        if memzone_len(&sizes) * size_of::<NodeIndex>() + leaves > arena_bytes {
+            return Err(CapacityError);
+        }
+        let nodes = (arena_bytes - leaves) / size_of::<NodeIndex>();
+        Self::compress_into(trie, &sizes, CompressedTree::bounded(nodes))
+    }
+
+    // compresses the trie according to the sizes computed by [`compressed_sizes`]
+    fn compress_into(trie: RadixTrie<K, V>, sizes: &[u8], branching: CompressedTree) -> Result<Self, CapacityError>
     {
         let mut lctrie = Self {
-            branching: CompressedTree::with_capacity(trie.branching.0.len()),
-            leaves: trie.leaves
+            branching,
+            leaves: trie.leaves,
+            patricia: trie.branching.0.len()
         };
         // compiling...
-        let comp = 0;
         let trie = trie.branching;
         let mut done = Vec::<Option<BranchingIndex>>::new();
         done.resize(trie.0.len(), None);
 
-        lctrie.compress(&trie, BranchingIndex::root(), BranchingIndex::root(), &mut done, comp);
+        lctrie.compress(&trie, BranchingIndex::root(), BranchingIndex::root(), &mut done, sizes)?;
         lctrie.skip_redundant_parent(BranchingIndex::root(), LeafIndex::root_leaf(), BranchingIndex::root());
-        lctrie.branching.memzone.shrink_to_fit();
-        lctrie.leaves.0.shrink_to_fit();
-        lctrie
+        Ok(lctrie)
+    }
+
+
+    /// Predicts the memory size (in bytes) of the compressed version of the trie
+    ///
+    /// The compressed nodes are sized by the same pass as the compression
+    /// (see [`compressed_sizes`]) so the prediction is exact, but nothing
+    /// is compressed.
+    pub(crate) fn estimated_bytes(trie: &RadixTrie<K, V>) -> usize
+    {
+        let sizes = compressed_sizes(&trie.branching, 0);
+        memzone_len(&sizes) * size_of::<NodeIndex>() + trie.leaves.len() * size_of::<Leaf<K,V>>()
     }
 
+    /// Memory size (in bytes) of the compressed nodes and the leaves
+    #[inline]
+    pub(crate) fn memory_bytes(&self) -> usize
+    {
+        self.branching.memzone.len() * size_of::<NodeIndex>() + self.leaves.len() * size_of::<Leaf<K,V>>()
+    }
+
+    /// Gathers the distribution of the sizes of the compressed nodes
+    pub(crate) fn compression_report(&self) -> CompressionReport
+    {
+        let mut sizes = [0; 17];
+        self.branching.iter().for_each(|(_,c)| sizes[c.size as usize] += 1);
+        CompressionReport {
+            nodes: sizes.iter().sum(),
+            sizes,
+            patricia_nodes: self.patricia
+        }
+    }
 
     pub fn map<W, F: FnMut(&V) -> W>(&self, mut f: F) -> LevelCompressedTrie<K, W>
     {
@@ -55,10 +125,25 @@ impl<K:IpPrefix,V>  LevelCompressedTrie<K,V> {
                         Leaf::new(*leaf.prefix(), f(leaf.get().1))
                     })
                     .collect()
-            )
+            ),
+            patricia: self.patricia
         }
     }
 
+    /// Rebuilds a Patricia trie with the same leaves (the root leaf included)
+    pub(crate) fn decompress(&self) -> RadixTrie<K,V>
+        where V: Clone
+    {
+        let mut leaves = self.leaves.0.iter().cloned().map(<(K,V)>::from);
+        let (root, value) = leaves.next().unwrap();
+        let mut trie = RadixTrie {
+            branching: BranchingTree::new(self.leaves.len()),
+            leaves: TrieLeaves::new(self.leaves.len(), root, value)
+        };
+        leaves.for_each(|(k,v)| { trie.insert(k,v); });
+        trie
+    }
+
     fn skip_redundant_parent(&mut self, b: BranchingIndex, esc: LeafIndex, up: BranchingIndex)
     {
         (0..self[b].children())
@@ -80,16 +165,17 @@ impl<K:IpPrefix,V>  LevelCompressedTrie<K,V> {
                 tree: &BranchingTree,
                 b: BranchingIndex, parent: BranchingIndex,
                 done: &mut Vec<Option<BranchingIndex>>, // the already known nodes (branching in radix trie => compressed in LC-trie)
-                comp: u8)
-                -> BranchingIndex
+                sizes: &[u8]) // the sizes of the compressed nodes (see compressed_sizes)
+                -> Result<BranchingIndex, CapacityError>
     {
-        let compression = tree.compression_level(&tree[b], comp);
+        let size = sizes[b.index()];
+        debug_assert!( size > 0 );
         let shift: u8 = tree[b].bit;
-        let current = self.branching.push(parent, tree[b].escape, shift - 1, compression + 1);
+        let current = self.branching.push(parent, tree[b].escape, shift - 1, size)?;
         done[b.index()] = current.into();
         let bb = &mut self[current];
-        (0..bb.children()).for_each(|i| self.compute_compressed_child(tree, current, i, 1, b, b, done, comp));
-        current
+        (0..bb.children()).try_for_each(|i| self.compute_compressed_child(tree, current, i, 1, b, b, done, sizes))?;
+        Ok(current)
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -101,8 +187,8 @@ impl<K:IpPrefix,V>  LevelCompressedTrie<K,V> {
                                 start: BranchingIndex, // the start point of the analysis (in the radix trie)
                                 mut b: BranchingIndex, // the current point of the analysis (in the radix trie)
                                 done: &mut Vec<Option<BranchingIndex>>, // the already known nodes (branching in radix trie => compressed in LC-trie)
-                                comp: u8) // the compression level: 0=>1bit (no compression), N=>N+1 bits
-    //-> NodeIndex
+                                sizes: &[u8]) // the sizes of the compressed nodes (see compressed_sizes)
+                                -> Result<(), CapacityError>
     {
         debug_assert_eq!(tree[start].escape, tree[b].escape);
 
@@ -134,16 +220,53 @@ impl<K:IpPrefix,V>  LevelCompressedTrie<K,V> {
                 if depth > c.size {
                     // ce fils est au dela du niveau de compression en cours...
                     // on passe donc a un nouveau noeud de branchement compresse
-                    *self[current].child_mut(currchild) = self.compress(tree, thechild, current, done, comp).into();
+                    *self[current].child_mut(currchild) = self.compress(tree, thechild, current, done, sizes)?.into();
                 } else {
                     //assert (start.escape == trie.branching[thechild].escape);
-                    self.compute_compressed_child(tree, current, currchild, depth, start, thechild, done, comp);
+                    self.compute_compressed_child(tree, current, currchild, depth, start, thechild, done, sizes)?;
                 }
             }
         }
+        Ok(())
     }
 }
 
+/// Sizes (in bits) of the compressed nodes, indexed by the branching of the Patricia trie
+/// which starts each of them (0 for a branching compressed in the node of an ancestor)
+///
+/// This is the only place where the compression heuristics are applied: both
+/// the compression and the prediction of its memory size rely on this pass.
+/// The compression level `comp` is 0 for 1 bit (no compression), N for N+1 bits.
+fn compressed_sizes(tree: &BranchingTree, comp: u8) -> Vec<u8>
+{
+    let mut sizes = vec![0; tree.0.len()];
+    let mut todo = vec![BranchingIndex::root()];
+    while let Some(b) = todo.pop() {
+        if sizes[b.index()] != 0 { continue; }
+        let size = tree.compression_level(&tree[b], comp) + 1;
+        sizes[b.index()] = size;
+        // the deeper branching are compressed in other nodes
+        let limit = tree[b].bit - 1 + size;
+        let mut inner = vec![b];
+        while let Some(n) = inner.pop() {
+            tree[n].child.iter()
+                .filter(|c| c.is_branching())
+                .map(|c| BranchingIndex::from(*c))
+                .for_each(|c| if tree[c].bit > limit { todo.push(c) } else { inner.push(c) });
+        }
+    }
+    sizes
+}
+
+/// Length of the memzone which stores the compressed nodes of the given sizes
+fn memzone_len(sizes: &[u8]) -> usize
+{
+    sizes.iter()
+        .filter(|&&size| size > 0)
+        .map(|&size| Compressed::offset(1 << size))
+        .sum()
+}
+
 impl<K:IpPrefix,V> LevelCompressedTrie<K,V>
 {
     #[inline]
@@ -187,7 +310,7 @@ impl<K:IpPrefix,V> LevelCompressedTrie<K,V>
     }
 
     #[inline]
-    fn inner_lookup<Q>(&self, k: &Q) -> LeafIndex
+    pub(crate) fn inner_lookup<Q>(&self, k: &Q) -> LeafIndex
         where
             Q: IpPrefix<Addr=K::Addr>,
             K: IpPrefixCovering<Q>
@@ -218,17 +341,111 @@ impl<K:IpPrefix,V> LevelCompressedTrie<K,V>
         l
     }
 
+    /// Checks the consistency of the compressed structure.
+    ///
+    /// All the node indices should be valid, each leaf should be reachable
+    /// exactly once and the lookup of each stored prefix should return its own leaf.
+    /// Returns the number of checked leaves.
+    pub fn check(&self) -> Result<usize, CompressError>
+    {
+        let mut nodes = vec![false; self.branching.memzone.len()];
+        self.branching.iter().for_each(|(i,_)| nodes[i.index()] = true);
+        let is_node = |i: BranchingIndex| nodes.get(i.index()).copied().unwrap_or(false);
+        let nleaves = self.leaves.len();
+        // the escape leaves are legitimately shared (by the subtree of their node)
+        let mut escape = vec![false; nleaves];
+        self.branching.iter()
+            .try_for_each(|(_,b)| {
+                if b.escape.index() < nleaves { escape[b.escape.index()] = true; Ok(()) } else { Err(CompressError::InvalidIndex) }
+            })?;
+        // the other ones are linked by only one node (possibly in several slots)
+        let mut reached = vec![false; nleaves];
+        let mut linking = vec![None; nleaves];
+        self.branching.iter()
+            .try_for_each(|(i,b)| {
+                if !is_node(b.parent) {
+                    return Err(CompressError::InvalidIndex);
+                }
+                reached[b.escape.index()] = true;
+                (0..b.children())
+                    .map(|n| *b.child(n))
+                    .try_for_each(|c| {
+                        if c.is_leaf() {
+                            let l = LeafIndex::from(c).index();
+                            if l >= nleaves {
+                                return Err(CompressError::InvalidIndex);
+                            }
+                            reached[l] = true;
+                            if !escape[l] {
+                                match linking[l] {
+                                    None => linking[l] = Some(i),
+                                    Some(j) if j != i => return Err(CompressError::DuplicateLeaf(l)),
+                                    _ => {}
+                                }
+                            }
+                            Ok(())
+                        } else if is_node(c.into()) {
+                            Ok(())
+                        } else {
+                            Err(CompressError::InvalidIndex)
+                        }
+                    })
+            })?;
+        if let Some(l) = reached.iter().position(|r| !r) {
+            return Err(CompressError::UnreachableLeaf(l));
+        }
+        (0..self.leaves.len())
+            .map(LeafIndex::from)
+            .try_for_each(|l| {
+                if self.inner_lookup(&self[l]) == l { Ok(()) } else { Err(CompressError::LookupMismatch(l.index())) }
+            })?;
+        Ok(self.leaves.len())
+    }
+
+    /// Visits all the leaves by walking the compressed tree in prefix order.
+    ///
+    /// Each leaf is visited once and a prefix is always visited
+    /// before the prefixes it covers.
+    pub fn for_each_preorder<F: FnMut(&K,&V)>(&self, mut f: F)
+    {
+        let mut visited = vec![false; self.leaves.len()];
+        self.visit_preorder(BranchingIndex::root(), &mut visited, &mut f);
+    }
+
+    fn visit_preorder<F: FnMut(&K,&V)>(&self, b: BranchingIndex, visited: &mut [bool], f: &mut F)
+    {
+        self.visit_leaf(self[b].escape, visited, f);
+        (0..self[b].children())
+            .map(|i| *self[b].child(i))
+            .for_each(|c| {
+                if c.is_leaf() {
+                    self.visit_leaf(c.into(), visited, f)
+                } else {
+                    self.visit_preorder(c.into(), visited, f)
+                }
+            });
+    }
+
+    #[inline]
+    fn visit_leaf<F: FnMut(&K,&V)>(&self, l: LeafIndex, visited: &mut [bool], f: &mut F)
+    {
+        if !visited[l.index()] {
+            visited[l.index()] = true;
+            let (k,v) = self.leaves[l].get();
+            f(k,v);
+        }
+    }
+
+    #[cfg(feature = "std")]
     pub fn info(&self)
     {
         println!("LC-TRIE info");
-        println!("{} branching, {} leaves", self.branching.iter().count(), self.leaves.len());
+        let report = self.compression_report();
+        println!("{} branching, {} leaves", report.nodes, self.leaves.len());
         println!("root: {} children (2^{}), {} shift", self.branching[0.into()].children(), self.branching[0.into()].size, self.branching[0.into()].shift);
 
-        let mut counts = [0;128];
-        self.branching.iter()
-            .for_each(|(_,c)| counts[c.size as usize] += 1 );
         print!("children:");
-        counts.iter().enumerate().filter(|(_,&c)| c !=0 )
+        report.sizes.iter().enumerate().filter(|(_,&c)| c !=0 )
             .for_each(|(n,&c)| print!(" {}->{}", (1<<n), c));
         println!();
 /*
@@ -246,8 +463,8 @@ impl<K:IpPrefix,V> LevelCompressedTrie<K,V>
             } );
         println!("shift: {:?}", counts);
 */
-        let branching =    self.branching.memzone.len() * std::mem::size_of::<NodeIndex>()/1000;
-        let leaves = self.leaves.len() * std::mem::size_of::<Leaf<K,V>>()/1000;
+        let branching =    self.branching.memzone.len() * size_of::<NodeIndex>()/1000;
+        let leaves = self.leaves.len() * size_of::<Leaf<K,V>>()/1000;
         println!("memory: {:?}k + {:?}k = {:?}k", branching, leaves, branching+leaves);
 
         println!();
@@ -279,6 +496,60 @@ impl<K: IpPrefix, V> IndexMut<BranchingIndex> for LevelCompressedTrie<K,V>
 }
 
 
+/// Error generated when a compressed trie is found inconsistent
+#[derive(Debug,PartialEq,Eq,Copy,Clone)]
+pub enum CompressError {
+    /// A compressed node refers to a node or a leaf which does not exist
+    InvalidIndex,
+    /// The leaf (identified by its position) is not referred by any compressed node
+    UnreachableLeaf(usize),
+    /// The leaf (identified by its position) is referred as a child by several compressed nodes
+    DuplicateLeaf(usize),
+    /// The lookup of the prefix of this leaf (identified by its position) does not return it
+    LookupMismatch(usize),
+}
+
+impl fmt::Display for CompressError
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompressError::InvalidIndex => {
+                fmt.write_str("compressed node refers to an invalid index")
+            }
+            CompressError::UnreachableLeaf(l) => {
+                write!(fmt, "leaf #{} is not reachable in the compressed trie", l)
+            }
+            CompressError::DuplicateLeaf(l) => {
+                write!(fmt, "leaf #{} is referred by several compressed nodes", l)
+            }
+            CompressError::LookupMismatch(l) => {
+                write!(fmt, "lookup of the prefix of leaf #{} does not return it", l)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CompressError {}
+
+
+/// Summary of the compression of a trie (see [`crate::LCTrieSet::compression_report`])
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressionReport {
+    /// Number of compressed nodes
+    pub nodes: usize,
+    /// Number of compressed nodes by size (a node of size `n` has `2^n` children)
+    pub sizes: [usize; 17],
+    /// Number of branching nodes of the Patricia trie before compression
+    pub patricia_nodes: usize,
+}
+
+impl CompressionReport {
+    /// Ratio of the compressed nodes to the Patricia branching nodes
+    #[inline]
+    pub fn ratio(&self) -> f64 { self.nodes as f64 / self.patricia_nodes as f64 }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub struct Compressed {
@@ -292,7 +563,7 @@ pub struct Compressed {
 
 impl fmt::Debug for Compressed
 {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "Compressed<...> node")?;
         writeln!(f, "  - shift:{}, size:{}, bitmask:{:b}", self.shift, self.size, self.mask)?;
         writeln!(f, "  - escape leaf:{:?}, parent:{:?}", self.escape, self.parent)?;
@@ -348,33 +619,46 @@ impl Compressed {
     }
 }
 
+// The nodes are only read through pointer casts of the memzone, which owns
+// plain indices: the derived `Send` and `Sync` are sound (no shared mutability).
 #[derive(Clone)]
 pub(crate) struct CompressedTree {
-    memzone: Vec<NodeIndex>
+    memzone: Vec<NodeIndex>,
+    // maximal length of the memzone (if bounded, it is never reallocated)
+    limit: Option<usize>
 }
 
 impl CompressedTree {
 
     pub fn with_capacity(n: usize) -> Self
     {
-        let mut memzone = Vec::new();
-        // todo: (n+1) ou n ?? ou autre chose ? comment est-ce calculé ?
-        memzone.resize((n+1) * (2 * size_of::<Compressed>() / size_of::<NodeIndex>()), NodeIndex::root());
-        unsafe { memzone.set_len(0) };
-        Self { memzone }
+        // this is only an estimation, the memzone grows if needed when pushing
+        let memzone = Vec::with_capacity((n+1) * (2 * size_of::<Compressed>() / size_of::<NodeIndex>()));
+        Self { memzone, limit: None }
     }
 
-    pub fn push(&mut self, parent: BranchingIndex, escape: LeafIndex, shift:u8, size:u8) -> BranchingIndex
+    /// Allocates once a memzone of `n` indices, which pushing never exceeds
+    pub fn bounded(n: usize) -> Self
     {
-        assert!( self.memzone.capacity() >= self.memzone.len() + Compressed::offset(1<<size));
+        Self { memzone: Vec::with_capacity(n), limit: Some(n) }
+    }
 
+    /// Fails if the memzone is bounded and has no room for this node
+    pub fn push(&mut self, parent: BranchingIndex, escape: LeafIndex, shift:u8, size:u8) -> Result<BranchingIndex, CapacityError>
+    {
+        let len = self.memzone.len() + Compressed::offset(1<<size);
+        if self.limit.is_some_and(|limit| len > limit) {
+            return Err(CapacityError);
+        }
+        // nodes are referred by their offsets in the memzone,
+        // so a reallocation (if capacity is exhausted) keeps them valid
         let index = self.memzone.len().into();
-        unsafe { self.memzone.set_len( self.memzone.len() + Compressed::offset(1<<size)); }
+        self.memzone.resize(len, NodeIndex::root());
 
         self[index] = Compressed::new(shift, size, escape, parent);
         (0..self[index].children())
             .for_each(|i| *self[index].child_mut(i) = self[index].escape.into());
-        index
+        Ok(index)
     }
 
     pub(crate) fn iter(&self) -> BranchingIterator<'_>
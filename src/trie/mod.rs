@@ -1,4 +1,5 @@
 pub(crate) mod common;
+pub(crate) mod storage;
 pub(crate) mod patricia;
 pub(crate) mod lctrie;
 
@@ -1,28 +1,37 @@
 
 
 #[cfg(feature= "graphviz")] use std::io;
-use std::num::NonZeroUsize;
-use std::ops::{Index, IndexMut};
+use core::num::NonZeroUsize;
+use core::ops::{Index, IndexMut};
+use alloc::{format, vec};
+use alloc::string::String;
+use alloc::vec::Vec;
 use crate::prefix::*;
 use super::common::*;
+use super::storage::{HeapStorage, NodeVec, TrieStorage};
 
-#[derive(Clone)]
-pub(crate) struct RadixTrie<K,V>
+pub(crate) struct RadixTrie<K,V,S: TrieStorage = HeapStorage>
 {
-    pub(crate) branching: BranchingTree,
-    pub(crate) leaves: TrieLeaves<Leaf<K,V>>
+    pub(crate) branching: BranchingTree<S>,
+    pub(crate) leaves: TrieLeaves<Leaf<K,V>,S>
 }
 
-impl<K,V> RadixTrie<K,V>
+impl<K,V,S: TrieStorage> Clone for RadixTrie<K,V,S>
+    where
+        TrieLeaves<Leaf<K,V>,S>: Clone,
+        BranchingTree<S>: Clone
 {
     #[inline]
-    pub fn iter(&self) -> impl Iterator<Item=&Leaf<K,V>> + '_ {
-        self.leaves.0.iter()
+    fn clone(&self) -> Self {
+        Self { branching: self.branching.clone(), leaves: self.leaves.clone() }
     }
+}
 
+impl<K,V,S: TrieStorage> RadixTrie<K,V,S>
+{
     #[inline]
-    pub fn iter_mut(&mut self) -> impl Iterator<Item=&mut Leaf<K,V>> + '_ {
-        self.leaves.0.iter_mut()
+    pub fn iter(&self) -> impl Iterator<Item=&Leaf<K,V>> + '_ {
+        self.leaves.0.iter()
     }
 
     #[inline]
@@ -32,21 +41,36 @@ impl<K,V> RadixTrie<K,V>
             NonZeroUsize::new_unchecked(self.leaves.len())
         }
     }
+}
 
+impl<K,V> RadixTrie<K,V>
+{
     #[inline]
     pub fn shrink_to_fit(&mut self) {
         self.leaves.0.shrink_to_fit();
         self.branching.0.shrink_to_fit();
     }
+
+    /// Removes all the entries except the root one and returns them
+    #[inline]
+    pub fn drain(&mut self) -> impl Iterator<Item=(K,V)> + '_ {
+        self.branching.clear();
+        self.leaves.0.drain(1..).map(<(K,V)>::from)
+    }
 }
 
-impl<K:IpRootPrefix,V> RadixTrie<K,V>
+impl<K:IpRootPrefix,V,S: TrieStorage> RadixTrie<K,V,S>
 {
     pub(crate) fn new(value: V, capacity: usize) -> Self
+    {
+        Self::with_capacities(value, capacity, capacity / 2)
+    }
+
+    pub(crate) fn with_capacities(value: V, leaves: usize, branching: usize) -> Self
     {
         Self {
-            branching: BranchingTree::new(capacity / 2),
-            leaves: TrieLeaves::new(capacity, K::root(), value)
+            branching: BranchingTree::new(branching),
+            leaves: TrieLeaves::new(leaves, K::root(), value)
         }
     }
 }
@@ -65,41 +89,108 @@ impl<K:IpPrefix,V> RadixTrie<K,V>
         }
     }
 
+    /// Same as [`Self::map`] but consumes the trie (so the branching are not cloned)
+    pub fn into_map<W, F: FnMut(&K, &V) -> W>(self, mut f: F) -> RadixTrie<K, W>
+    {
+        RadixTrie {
+            branching: self.branching,
+            leaves: TrieLeaves(
+                self.leaves.0.into_iter()
+                    .map(|leaf| {
+                        let (k, v) = leaf.get();
+                        Leaf::new(*k, f(k, v))
+                    })
+                    .collect()
+            )
+        }
+    }
+}
+
+impl<K:IpPrefix,V,S: TrieStorage> RadixTrie<K,V,S>
+{
+
     pub fn insert(&mut self, k: K, v: V) -> Option<V>
     {
-        let addedleaf = self.leaves.push(Leaf::new(k, v));
-        let addedpfx = self[addedleaf];
+        match self.insertion_point(&k) {
+            Ok(l) => Some(core::mem::replace(self.leaves[l].get_mut().1, v)),
+            Err((deepestbranching, deepestleaf)) => {
+                let addedleaf = self.leaves.push(Leaf::new(k, v));
+                self.insert_leaf(addedleaf, deepestbranching, deepestleaf);
+                None
+            }
+        }
+    }
 
-        let (deepestbranching, deepestleaf) = self.branching.search_deepest_candidate(&addedpfx.bitslot());
+    // gets the value of the key, inserting it if absent (with a single descent)
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, k: K, f: F) -> &mut V
+    {
+        let l = match self.insertion_point(&k) {
+            Ok(l) => l,
+            Err((deepestbranching, deepestleaf)) => {
+                let addedleaf = self.leaves.push(Leaf::new(k, f()));
+                self.insert_leaf(addedleaf, deepestbranching, deepestleaf);
+                addedleaf
+            }
+        };
+        self.leaves[l].get_mut().1
+    }
+
+    // finds the leaf with the same range as the key or, if none,
+    // the deepest candidates from where a new leaf should be linked
+    fn insertion_point(&self, k: &K) -> Result<LeafIndex, (BranchingIndex, LeafIndex)>
+    {
+        let (deepestbranching, deepestleaf) = self.branching.search_deepest_candidate(&k.bitslot());
         let mut l = deepestleaf;
         let mut b = deepestbranching;
-        if l != self[b].escape && !self[l].covers(&addedpfx) {
+        if l != self[b].escape && !self[l].covers(k) {
             l = self[b].escape;
         }
         // will stop since the top prefix always matches
         loop {
-            match self[l].covering(&addedpfx) {
+            match self[l].covering(k) {
                 IpPrefixCoverage::NoCover => {
                     assert!(!l.is_root_leaf());
                     b = self[b].parent;
                     l = self[b].escape;
                 }
-                IpPrefixCoverage::WiderRange => {
-                    self.branching.insert_prefix(addedleaf, &addedpfx.bitslot(), addedpfx.len(),
-                                                 deepestbranching, deepestleaf,
-                                                 &self[deepestleaf].bitslot(), self[deepestleaf].len());
-                    return None
-                }
-                IpPrefixCoverage::SameRange => {
-                    let leaf = self.leaves.remove_last().unwrap();
-                    let mut v = <Leaf<K,V> as Into<(K,V)>>::into(leaf).1;
-                    std::mem::swap(&mut v, self.leaves[l].get_mut().1);
-                    return Some(v);
-                }
+                IpPrefixCoverage::WiderRange => return Err((deepestbranching, deepestleaf)),
+                IpPrefixCoverage::SameRange => return Ok(l),
             }
         }
     }
 
+    // an insertion adds one leaf and at most one branching
+    pub fn try_insert(&mut self, k: K, v: V) -> Result<Option<V>, crate::CapacityError>
+    {
+        check_index(self.leaves.len())?;
+        check_index(self.branching.0.len())?;
+        Ok(self.insert(k, v))
+    }
+
+    // links a new leaf in the branching tree
+    fn insert_leaf(&mut self, addedleaf: LeafIndex, deepestbranching: BranchingIndex, mut deepestleaf: LeafIndex)
+    {
+        let addedpfx = self[addedleaf];
+        if deepestleaf == self[deepestbranching].escape {
+            // after some removals, the escape leaf does not necessarily share the bits skipped
+            // by this branching, so we check them against a leaf of its subtree
+            // (or of the subtree of an ancestor if it remains no leaf there)
+            let mut b = deepestbranching;
+            let mut leaf = self.branching.search_one_matching_leaf(b);
+            while leaf == self[self[b].parent].escape && !b.is_root() {
+                b = self[b].parent;
+                leaf = self.branching.search_one_matching_leaf(b);
+            }
+            if (addedpfx.bitslot() ^ self[leaf].bitslot()).first_bit() < self[b].bit {
+                deepestleaf = leaf;
+            }
+        }
+        self.branching.insert_prefix(addedleaf, &addedpfx.bitslot(), addedpfx.len(),
+                                     deepestbranching, deepestleaf,
+                                     &self[deepestleaf].bitslot(), self[deepestleaf].len());
+        debug_assert!( self.check_parent_chain(self.inner_lookup(&addedpfx).0).is_ok() );
+    }
+
     pub fn replace(&mut self, k: K, v: V) -> Option<Leaf<K,V>>
     {
         let addedleaf = self.leaves.push(Leaf::new(k, v));
@@ -120,14 +211,12 @@ impl<K:IpPrefix,V> RadixTrie<K,V>
                     l = self[b].escape;
                 }
                 IpPrefixCoverage::WiderRange => {
-                    self.branching.insert_prefix(addedleaf, &addedpfx.bitslot(), addedpfx.len(),
-                                                 deepestbranching, deepestleaf,
-                                                 &self[deepestleaf].bitslot(), self[deepestleaf].len());
+                    self.insert_leaf(addedleaf, deepestbranching, deepestleaf);
                     return None;
                 }
                 IpPrefixCoverage::SameRange => {
                     let mut v = self.leaves.remove_last().unwrap();
-                    std::mem::swap(&mut v, &mut self.leaves[l]);
+                    core::mem::swap(&mut v, &mut self.leaves[l]);
                     return Some(v);
                 }
             }
@@ -135,7 +224,7 @@ impl<K:IpPrefix,V> RadixTrie<K,V>
     }
 }
 
-impl<K:IpPrefix,V> RadixTrie<K,V>
+impl<K:IpPrefix,V,S: TrieStorage> RadixTrie<K,V,S>
 {
     pub fn get<Q>(&self, k: &Q) -> Option<(&K,&V)>
         where
@@ -183,17 +272,26 @@ impl<K:IpPrefix,V> RadixTrie<K,V>
             // todo: some branching possibly becomes useless and should be removed here
 
             // reindex the leaf which will be swapped with the removed one
+            // (nothing to do if the removed leaf is the last one)
             let lastleaf = LeafIndex::from(self.leaves.len()-1);
-            let (mut bb,_ll) = self.inner_lookup(&self[lastleaf]);
-            debug_assert_eq!( self[lastleaf].len(), self[_ll].len() );
-            if self[bb].child[0] == lastleaf { self[bb].child[0] = l.into(); }
-            if self[bb].child[1] == lastleaf { self[bb].child[1] = l.into(); }
-            while self[bb].escape == lastleaf {
-                self[bb].escape = l;
-                bb = self[bb].parent; // climb up the escape chain
+            if lastleaf != l {
+                let (mut bb,_ll) = self.inner_lookup(&self[lastleaf]);
+                debug_assert_eq!( self[lastleaf].len(), self[_ll].len() );
+                if self[bb].escape == lastleaf {
+                    // it is also the escape of branching out of its lookup path, so
+                    // climb to its first appearance and replace it in the whole subtree
+                    while self[self[bb].parent].escape == lastleaf {
+                        bb = self[bb].parent;
+                    }
+                    self.branching.replace_escape_leaf(bb, lastleaf, l);
+                } else {
+                    if self[bb].child[0] == lastleaf { self[bb].child[0] = l.into(); }
+                    if self[bb].child[1] == lastleaf { self[bb].child[1] = l.into(); }
+                }
             }
             // effective removal of the leaf
             let removed = self.leaves.0.swap_remove(l.index());
+            debug_assert!( self.check_parent_chain(b).is_ok() );
             Some(<Leaf<K,V> as Into<(K,V)>>::into(removed).1)
         }
     }
@@ -219,6 +317,237 @@ impl<K:IpPrefix,V> RadixTrie<K,V>
     }
 
 
+    /// Iterates over the leaves which cover the key, from the longest prefix to the root one.
+    pub(crate) fn covering_leaves<'a,Q>(&'a self, k: &'a Q) -> impl Iterator<Item=LeafIndex> + 'a
+        where
+            Q: IpPrefix<Addr=K::Addr>,
+            K: IpPrefixCovering<Q>
+    {
+        core::iter::successors(Some(self.inner_lookup(k)), move |&(mut b, l)| {
+            if l.is_root_leaf() { return None; }
+            // the next covering leaf is the first shorter escape which matches
+            // (will stop since the root leaf always matches)
+            loop {
+                let e = self[b].escape;
+                if self[e].len() < self[l].len() && self[e].covers(k) {
+                    return Some((b, e));
+                }
+                debug_assert!( !b.is_root() );
+                b = self[b].parent;
+            }
+        }).map(|(_,l)| l)
+    }
+
+    /// Collects the leaves which are covered by the key (including the key itself, if any).
+    pub(crate) fn covered_leaves(&self, k: &K) -> Vec<LeafIndex>
+    {
+        // first, reach the subtree of all the prefixes longer than the key
+        let mut b = BranchingIndex::root();
+        while self[b].bit <= k.len() {
+            match self[b].child(&k.bitslot_trunc()) {
+                n if n.is_branching() => b = n.into(),
+                n => {
+                    let l = LeafIndex::from(n);
+                    return if k.covers(&self[l]) { vec![l] } else { vec![] };
+                }
+            }
+        }
+        // then, gather all the leaves of this subtree
+        let mut leaves = vec![];
+        let mut stack = vec![b];
+        while let Some(b) = stack.pop() {
+            leaves.push(self[b].escape);
+            self[b].child.iter()
+                .for_each(|&c| if c.is_leaf() { leaves.push(c.into()) } else { stack.push(c.into()) });
+        }
+        leaves.retain(|&l| k.covers(&self[l]));
+        leaves.sort_unstable_by_key(LeafIndex::index);
+        leaves.dedup();
+        leaves
+    }
+
+    /// Collects the leaves which are not covered by the key (the root one excepted).
+    ///
+    /// Only the path of the key is walked: the leaves of the subtrees which branch
+    /// out of it are collected without any coverage test.
+    pub(crate) fn uncovered_leaves(&self, k: &K) -> Vec<LeafIndex>
+    {
+        let mut outside = vec![]; // the leaves which are not covered, for sure
+        let mut subtrees = Vec::<BranchingIndex>::new(); // the subtrees out of the path of the key
+        let mut tested = vec![]; // the leaves to check against the key
+        let mut b = BranchingIndex::root();
+        while self[b].bit <= k.len() {
+            // the escape is shorter than the key, so it is not covered
+            let bb = &self[b];
+            outside.push(bb.escape);
+            let on = bb.child(&k.bitslot_trunc());
+            bb.child.iter()
+                .filter(|&&c| c != on)
+                .for_each(|&c| if c.is_leaf() { outside.push(c.into()) } else { subtrees.push(c.into()) });
+            if on.is_leaf() {
+                tested.push(on.into());
+                break;
+            }
+            b = on.into();
+        }
+        if self[b].bit > k.len() {
+            // the subtree of all the prefixes longer than the key
+            let mut stack = vec![b];
+            while let Some(b) = stack.pop() {
+                tested.push(self[b].escape);
+                self[b].child.iter()
+                    .for_each(|&c| if c.is_leaf() { tested.push(c.into()) } else { stack.push(c.into()) });
+            }
+        }
+        while let Some(b) = subtrees.pop() {
+            outside.push(self[b].escape);
+            self[b].child.iter()
+                .for_each(|&c| if c.is_leaf() { outside.push(c.into()) } else { subtrees.push(c.into()) });
+        }
+        tested.retain(|&l| !k.covers(&self[l]));
+        outside.extend(tested);
+        outside.retain(|l| !l.is_root_leaf());
+        outside.sort_unstable_by_key(LeafIndex::index);
+        outside.dedup();
+        outside
+    }
+
+    /// Searches the next child of a leaf (a leaf of which it is the longest other cover),
+    /// in the order of the network addresses, after the given child (or the first one).
+    ///
+    /// The branching nodes are walked in place: a subtree is skipped as soon as
+    /// its escape leaf is a child, since all its other leaves are then covered by it.
+    pub(crate) fn next_child_leaf(&self, l: LeafIndex, after: Option<LeafIndex>) -> Option<LeafIndex>
+    {
+        let k = &self[l];
+        let is_child = |c: LeafIndex| c != l && k.covers(&self[c]);
+        // first, reach the subtree of all the prefixes longer than the key
+        let mut top = BranchingIndex::root();
+        while self[top].bit <= k.len() {
+            match self[top].child(&k.bitslot_trunc()) {
+                n if n.is_branching() => top = n.into(),
+                n => {
+                    // a single leaf, so a single child at most
+                    let c = LeafIndex::from(n);
+                    return (after.is_none() && is_child(c)).then_some(c);
+                }
+            }
+        }
+        // then, resume the depth-first walk of this subtree: (b,i) is the next child
+        // slot to visit in the branching b (the branching is completed when i == 2)
+        let (mut b, mut i) = match after {
+            None if is_child(self[top].escape) => return Some(self[top].escape),
+            None => (top, 0),
+            Some(c) => self.position_after(c),
+        };
+        loop {
+            if i == 2 {
+                if b == top { return None; }
+                let p = self[b].parent;
+                i = if self[p].child[0] == NodeIndex::from(b) { 1 } else { 2 };
+                b = p;
+            } else {
+                let c = self[b].child[i];
+                i += 1;
+                if c.is_leaf() {
+                    if is_child(c.into()) { return Some(c.into()); }
+                } else {
+                    b = c.into();
+                    i = 0;
+                    if is_child(self[b].escape) { return Some(self[b].escape); }
+                }
+            }
+        }
+    }
+
+    // the position of the walk of next_child_leaf just after the given leaf:
+    // after its first appearance as an escape (the whole subtree is skipped)
+    // or after the child slot which holds it
+    fn position_after(&self, l: LeafIndex) -> (BranchingIndex, usize)
+    {
+        let slot = self[l].bitslot_trunc();
+        let mut b = BranchingIndex::root();
+        loop {
+            if self[b].escape == l {
+                return (b, 2);
+            }
+            debug_assert!(self[b].bit <= self[l].len());
+            match self[b].child(&slot) {
+                n if n.is_branching() => b = n.into(),
+                n => {
+                    debug_assert_eq!(LeafIndex::from(n), l);
+                    let i = self[b].child.iter().position(|&c| c == n).unwrap();
+                    return (b, i + 1);
+                }
+            }
+        }
+    }
+
+    /// Checks if the key covers at least one leaf (the root one excepted).
+    pub(crate) fn covers_any_leaf<Q>(&self, k: &Q) -> bool
+        where
+            Q: IpPrefix<Addr=K::Addr> + IpPrefixCovering<K>
+    {
+        // first, reach the subtree of all the prefixes longer than the key
+        let mut b = BranchingIndex::root();
+        while self[b].bit <= k.len() {
+            match self[b].child(&k.bitslot_trunc()) {
+                n if n.is_branching() => b = n.into(),
+                n => {
+                    let l = LeafIndex::from(n);
+                    return !l.is_root_leaf() && k.covers(&self[l]);
+                }
+            }
+        }
+        // then, stop at the first covered leaf of this subtree (without allocating)
+        self.branching.find_leaf_in_subtree(b, |l| !l.is_root_leaf() && k.covers(&self[l])).is_some()
+    }
+
+    /// Lists the bits checked by the branching nodes along the descent to the key (useful for debugging).
+    ///
+    /// The descent stops at the branching which holds the prefix, either as a child
+    /// or as its escape leaf (first appearance). If the key is not stored, the path
+    /// leads to its longest prefix match.
+    #[allow(dead_code)]
+    pub(crate) fn bit_path<Q>(&self, k: &Q) -> Vec<u8>
+        where
+            Q: IpPrefix<Addr=K::Addr>,
+            K: IpPrefixCovering<Q>
+    {
+        let (mut b, l) = self.inner_lookup(k);
+        // an escape leaf is inherited by the subtree, so climb to its first appearance
+        while !b.is_root() && self[self[b].parent].escape == l {
+            b = self[b].parent;
+        }
+        let mut path = vec![self[b].bit];
+        while !b.is_root() {
+            b = self[b].parent;
+            path.push(self[b].bit);
+        }
+        path.reverse();
+        path
+    }
+
+    /// Lists the leaves sorted by address (then by length) with their depth of coverage
+    /// (i.e. the number of other stored prefixes which cover them).
+    pub(crate) fn hierarchy(&self) -> Vec<(usize, LeafIndex)>
+    {
+        let mut leaves = (0..self.leaves.len()).map(LeafIndex::from).collect::<Vec<_>>();
+        leaves.sort_by(|&a, &b| cmp_prefixes(&self[a], &self[b]));
+        let mut ancestors: Vec<LeafIndex> = vec![];
+        leaves.into_iter()
+            .map(|l| {
+                while let Some(&a) = ancestors.last() {
+                    if self[a].len() <= self[l].len() && self[a].contains_bits(self[l].bitslot_trunc()) { break; }
+                    ancestors.pop();
+                }
+                let depth = ancestors.len();
+                ancestors.push(l);
+                (depth, l)
+            })
+            .collect()
+    }
+
     #[inline]
     pub fn lookup<Q>(&self, k: &Q) -> (&K, &V)
         where
@@ -239,19 +568,178 @@ impl<K:IpPrefix,V> RadixTrie<K,V>
         self.leaves[l].get_mut()
     }
 
+    /// Checks that the parent chain of the branching climbs to the root
+    /// (without any cycle) with escape prefixes which get shorter.
+    fn check_parent_chain(&self, mut b: BranchingIndex) -> Result<(), String>
+    {
+        for _ in 0..self.branching.0.len() {
+            if b.is_root() { return Ok(()); }
+            let parent = self[b].parent;
+            if self[self[b].escape].len() < self[self[parent].escape].len() {
+                return Err(format!("branching {:?} escapes to a shorter prefix than its parent", b));
+            }
+            b = parent;
+        }
+        Err(format!("branching {:?} does not climb to the root", b))
+    }
+
+    /// Panics if the parent chain of some branching does not climb to the root.
+    ///
+    /// This is a cheaper check than [`Self::check_invariants`] (useful for tests).
+    /// In debug mode, only the chain of the modified branching is checked
+    /// after each insertion or removal.
+    #[allow(dead_code)]
+    pub(crate) fn debug_validate(&self)
+    {
+        if let Err(e) = (0..self.branching.0.len()).map(BranchingIndex::from).try_for_each(|b| self.check_parent_chain(b)) {
+            panic!("invalid trie: {}", e);
+        }
+    }
+
+    /// Gets the length of the longest run of consecutive branching sharing the same escape leaf.
+    ///
+    /// A lookup (or a removal) which falls back on an escape leaf
+    /// may climb such a run, so it bounds their cost.
+    pub(crate) fn max_escape_chain(&self) -> usize
+    {
+        let mut max = 0;
+        let mut stack = vec![(BranchingIndex::root(), 1)];
+        while let Some((b, run)) = stack.pop() {
+            max = max.max(run);
+            self[b].child.iter()
+                .filter(|c| c.is_branching())
+                .map(|&c| BranchingIndex::from(c))
+                .for_each(|c| stack.push((c, if self[c].escape == self[b].escape { run + 1 } else { 1 })));
+        }
+        max
+    }
+
+    /// Checks the consistency of the trie structure (useful for fuzzing).
+    #[cfg(any(test, debug_assertions))]
+    pub(crate) fn check_invariants(&self) -> Result<(), String>
+    {
+        let nbranching = self.branching.0.len();
+        let nleaves = self.leaves.len();
+        let valid_leaf = |l: LeafIndex| l.index() < nleaves;
+
+        if self.branching.0.is_empty() || nleaves == 0 {
+            return Err("missing root node".into());
+        }
+        if !self.branching.0[0].escape.is_root_leaf() {
+            return Err(format!("root branching escapes to leaf {:?}", self.branching.0[0].escape));
+        }
+        // all the indices are in range
+        self.branching.0.iter().enumerate()
+            .try_for_each(|(i,b)| {
+                if b.parent.index() >= nbranching {
+                    return Err(format!("branching {} has invalid parent {:?}", i, b.parent));
+                }
+                if !valid_leaf(b.escape) {
+                    return Err(format!("branching {} has invalid escape leaf {:?}", i, b.escape));
+                }
+                b.child.iter().try_for_each(|&c| {
+                    let valid = if c.is_leaf() { valid_leaf(c.into()) } else { BranchingIndex::from(c).index() < nbranching };
+                    if valid { Ok(()) } else { Err(format!("branching {} has invalid child {:?}", i, c)) }
+                })
+            })?;
+        // the bit positions are consistent with the prefix lengths
+        self.branching.0.iter().enumerate()
+            .try_for_each(|(i,b)| {
+                if self[b.escape].len() >= b.bit {
+                    return Err(format!("branching {} escapes to a too long prefix", i));
+                }
+                b.child.iter().try_for_each(|&c| {
+                    if c.is_leaf() {
+                        if c != b.escape && self[LeafIndex::from(c)].len() < b.bit {
+                            return Err(format!("branching {} has a too short child prefix {:?}", i, c));
+                        }
+                    } else if self.branching[c].parent.index() != i || self.branching[c].bit <= b.bit {
+                        return Err(format!("branching {} has an inconsistent child branching {:?}", i, c));
+                    }
+                    Ok(())
+                })
+            })?;
+        // all the escape chains climb up to the root leaf
+        (0..nbranching).map(BranchingIndex::from)
+            .try_for_each(|b| self.check_parent_chain(b))?;
+        // all the leaves are reachable from the root
+        let mut reached = vec![false; nleaves];
+        let mut stack = vec![BranchingIndex::root()];
+        let mut visited = 0;
+        while let Some(b) = stack.pop() {
+            visited += 1;
+            if visited > nbranching {
+                return Err("the branching nodes contain a cycle".into());
+            }
+            reached[self[b].escape.index()] = true;
+            self[b].child.iter()
+                .for_each(|&c| if c.is_leaf() { reached[LeafIndex::from(c).index()] = true } else { stack.push(c.into()) });
+        }
+        if let Some(l) = reached.iter().position(|r| !r) {
+            return Err(format!("leaf {} is unreachable", l));
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
     pub fn info(&self)
     {
         println!("PATRICIA TRIE info");
         println!("{} branching, {} leaves", self.branching.0.len(), self.leaves.len());
 
-        let branching =    self.branching.0.len() * std::mem::size_of::<Branching>()/1000;
-        let leaves = self.leaves.len() * std::mem::size_of::<Leaf<K,V>>()/1000;
+        let branching =    self.branching.0.len() * core::mem::size_of::<Branching>()/1000;
+        let leaves = self.leaves.len() * core::mem::size_of::<Leaf<K,V>>()/1000;
         println!("memory: {:?}k + {:?}k = {:?}k", branching, leaves, branching+leaves);
 
         println!();
     }
 }
 
+impl<K:IpPrefixFromSlot,V,S: TrieStorage> RadixTrie<K,V,S>
+{
+    /// Gathers the prefixes which cover exactly the addresses of the key
+    /// which are not covered by a prefix of this trie (the root one excepted).
+    pub(crate) fn uncovered<F: FnMut(K)>(&self, k: K, f: &mut F)
+    {
+        if !self.inner_lookup(&k).1.is_root_leaf() {
+            // fully covered by a stored prefix
+        } else if !self.covers_any_leaf(&k) {
+            f(k)
+        } else if let Some((k0, k1)) = k.split() {
+            self.uncovered(k0, f);
+            self.uncovered(k1, f);
+        }
+    }
+
+    /// Searches (in address order) the first prefix of the given length within the key
+    /// which is located after the bound and which does not intersect any prefix of this trie
+    /// (the root one excepted).
+    pub(crate) fn first_free(&self, k: K, after: &K, len: u8) -> Option<K>
+    {
+        let diff = (k.bitslot_trunc() ^ after.bitslot_trunc()) & K::Slot::bitmask(k.len().min(after.len()));
+        let beyond = if diff != K::Slot::default() {
+            if after.bitslot_trunc().is_set(diff.first_bit()) {
+                return None; // located before the bound
+            }
+            true
+        } else if k.len() >= after.len() {
+            return None; // located within the bound
+        } else {
+            false // contains the bound
+        };
+        if !self.inner_lookup(&k).1.is_root_leaf() {
+            None // fully covered by a stored prefix
+        } else if beyond && !self.covers_any_leaf(&k) {
+            Some(K::from_slot(k.bitslot_trunc(), len))
+        } else if k.len() >= len {
+            None
+        } else {
+            let (k0, k1) = k.split()?;
+            self.first_free(k0, after, len).or_else(|| self.first_free(k1, after, len))
+        }
+    }
+}
+
 
 #[cfg(feature= "graphviz")]
 impl<K:std::fmt::Display, V> crate::trie::graphviz::DotWriter for RadixTrie<K,V>
@@ -297,20 +785,20 @@ impl<K:std::fmt::Display, V> crate::trie::graphviz::DotWriter for RadixTrie<K,V>
 }
 
 
-impl<K,V> Index<BranchingIndex> for RadixTrie<K,V>
+impl<K,V,S: TrieStorage> Index<BranchingIndex> for RadixTrie<K,V,S>
 {
     type Output = Branching;
     #[inline]
     fn index(&self, i: BranchingIndex) -> &Self::Output { &self.branching[i] }
 }
 
-impl<K,V> IndexMut<BranchingIndex> for RadixTrie<K,V>
+impl<K,V,S: TrieStorage> IndexMut<BranchingIndex> for RadixTrie<K,V,S>
 {
     #[inline]
     fn index_mut(&mut self, i: BranchingIndex) -> &mut Self::Output { &mut self.branching[i] }
 }
 
-impl<K,V> Index<LeafIndex> for RadixTrie<K,V>
+impl<K,V,S: TrieStorage> Index<LeafIndex> for RadixTrie<K,V,S>
 {
     type Output = K;
     #[inline]
@@ -339,14 +827,20 @@ impl Branching {
     }
 }
 
-#[derive(Clone)]
-pub(crate) struct BranchingTree(pub(crate) Vec<Branching>);
+pub(crate) struct BranchingTree<S: TrieStorage = HeapStorage>(pub(crate) S::Vec<Branching>);
+
+impl<S: TrieStorage> Clone for BranchingTree<S>
+    where S::Vec<Branching>: Clone
+{
+    #[inline]
+    fn clone(&self) -> Self { Self(self.0.clone()) }
+}
 
-impl BranchingTree
+impl<S: TrieStorage> BranchingTree<S>
 {
     pub fn new(capacity: usize) -> Self
     {
-        let mut branching = Vec::with_capacity(capacity);
+        let mut branching = S::Vec::with_capacity(capacity);
         branching.push(Branching {
             escape: LeafIndex::root_leaf(),
             parent: BranchingIndex::root(),
@@ -356,7 +850,6 @@ impl BranchingTree
         Self(branching)
     }
 
-    #[allow(dead_code)]
     pub fn clear(&mut self)
     {
         self.0.clear();
@@ -371,6 +864,7 @@ impl BranchingTree
     // returns the index of the added node
     pub fn push(&mut self, parent: BranchingIndex, escape: LeafIndex, bit: u8) -> BranchingIndex
     {
+        assert!( check_index(self.0.len()).is_ok(), "too many branching nodes in the trie");
         let index = self.0.len().into();
         self.0.push(Branching {
             escape,
@@ -410,28 +904,43 @@ impl BranchingTree
         }
     }
 
-    #[allow(dead_code)]
-    pub fn search_one_matching_leaf(&self, mut b: BranchingIndex) -> LeafIndex
+    // searches a leaf of the subtree which is not inherited from the ancestors
+    // (returns the inherited escape leaf if there is no such leaf)
+    pub fn search_one_matching_leaf(&self, b: BranchingIndex) -> LeafIndex
     {
-        loop {
-            let bb = &self[b];
-            if self[bb.parent].escape != bb.escape {
-                return bb.escape;
+        let inherited = self[self[b].parent].escape;
+        self.find_leaf_in_subtree(b, |l| l != inherited).unwrap_or(inherited)
+    }
+
+    // searches a leaf of the subtree (an escape or a child) which matches the predicate
+    pub fn find_leaf_in_subtree<F: FnMut(LeafIndex) -> bool>(&self, b: BranchingIndex, mut f: F) -> Option<LeafIndex>
+    {
+        // depth-first walk which climbs back through the parent links,
+        // so nothing is allocated (as required by the fixed size storage)
+        let mut n = b;
+        'down: loop {
+            let bb = &self[n];
+            if f(bb.escape) {
+                return Some(bb.escape);
             }
-            if bb.child[0].is_leaf() && bb.child[0] != bb.escape {
-                return bb.child[0].into();
+            if let Some(&c) = bb.child.iter().find(|&&c| c.is_leaf() && f(c.into())) {
+                return Some(c.into());
             }
-            if bb.child[1].is_leaf() && bb.child[1] != bb.escape {
-                return bb.child[1].into();
+            if let Some(&c) = bb.child.iter().find(|c| c.is_branching()) {
+                n = c.into();
+                continue;
             }
-            if bb.child[0].is_branching() {
-                b = bb.child[0].into();
-            } else if bb.child[1].is_branching() {
-                b = bb.child[1].into();
-            } else {
-                debug_assert!(b.is_root()); // empty trie...
-                return LeafIndex::root_leaf();
+            // climb up to the first ancestor whose second child is not yet visited
+            while n != b {
+                let p = self[n].parent;
+                let [first, second] = self[p].child;
+                if first == n && second.is_branching() {
+                    n = second.into();
+                    continue 'down;
+                }
+                n = p;
             }
+            return None;
         }
     }
 
@@ -494,29 +1003,20 @@ impl BranchingTree
 
         if (pos > deepestlen) && (deepestlen < addedlen) {
             // tout se joue au dela du prefixe le plus long dans le trie
-            if self[n].child(addedslot) == self[n].escape {
+            if self[n].child(addedslot) != self[n].escape {
+                self.insert_prefix_branching(n, deepestindex, addedindex.into(), deepestlen+1, addedslot);
+            } else if self[n].bit <= addedlen {
                 *self[n].child_mut(addedslot) = addedindex.into();
             } else {
-                self.insert_prefix_branching(n, deepestindex, addedindex.into(), deepestlen+1, addedslot);
+                // the branching is deeper than the added prefix (it remains after
+                // some removals) so the added prefix becomes an escape
+                self.insert_escape_prefix(n, addedindex, addedlen, addedslot);
             }
         } else if pos > addedlen {
             // on sait que le deepest est plus long (il est plus long que pos donc de addedlength), donc on sait que
             // le prefixe ajoute est un prefixe de deepest (sinon pos serait plus petite)
             // reste a l'inserer s'il n'est pas deja present
-            let pos = addedlen + 1;
-            while self[n].bit > pos {
-                n = self[n].parent;
-            }
-
-            // ici, sauf erreur, la longueur du prefixe de b->escape n'est pas egale
-            // a addedlength sinon, cela voudrait dire que le prefixe ajoute etati
-            if self[n].bit < pos {
-                // il faut inserer un branchement avec la bonne position
-                self.insert_prefix_branching(n, addedindex, self[n].child(deepestslot), pos, deepestslot);
-            } else {
-                debug_assert_eq!(self[n].bit, pos);
-                self.replace_escape_leaf(n, self[n].escape, addedindex);
-            }
+            self.insert_escape_prefix(n, addedindex, addedlen, deepestslot);
         } else {
             // bon, la, on sait que la position discriminante est inferieure a la longueur
             // de chacun des prefixes donc ils sont bien concurrents
@@ -539,6 +1039,26 @@ impl BranchingTree
         }
     }
 
+    // inserts the prefix as the escape leaf of the branching testing the bit just after it
+    // (the branching is created if needed, above n)
+    fn insert_escape_prefix<B:BitSlot>(&mut self, mut n: BranchingIndex, addedindex: LeafIndex, addedlen: u8, slot: &B)
+    {
+        let pos = addedlen + 1;
+        while self[n].bit > pos {
+            n = self[n].parent;
+        }
+
+        // ici, sauf erreur, la longueur du prefixe de b->escape n'est pas egale
+        // a addedlength sinon, cela voudrait dire que le prefixe ajoute etati
+        if self[n].bit < pos {
+            // il faut inserer un branchement avec la bonne position
+            self.insert_prefix_branching(n, addedindex, self[n].child(slot), pos, slot);
+        } else {
+            debug_assert_eq!(self[n].bit, pos);
+            self.replace_escape_leaf(n, self[n].escape, addedindex);
+        }
+    }
+
     // this is the number of suppressed branching if compression is done
     // note: this node is counted also
     pub(crate) fn count_compressed_branching(&self, b: &Branching, p: u8) -> usize
@@ -603,7 +1123,7 @@ impl BranchingTree
 }
 
 
-impl Index<BranchingIndex> for BranchingTree
+impl<S: TrieStorage> Index<BranchingIndex> for BranchingTree<S>
 {
     type Output = Branching;
 
@@ -615,7 +1135,7 @@ impl Index<BranchingIndex> for BranchingTree
     }
 }
 
-impl IndexMut<BranchingIndex> for BranchingTree
+impl<S: TrieStorage> IndexMut<BranchingIndex> for BranchingTree<S>
 {
     #[inline]
     fn index_mut(&mut self, i: BranchingIndex) -> &mut Self::Output
@@ -626,7 +1146,7 @@ impl IndexMut<BranchingIndex> for BranchingTree
 }
 
 
-impl Index<NodeIndex> for BranchingTree
+impl<S: TrieStorage> Index<NodeIndex> for BranchingTree<S>
 {
     type Output = Branching;
 
@@ -639,7 +1159,7 @@ impl Index<NodeIndex> for BranchingTree
     }
 }
 
-impl IndexMut<NodeIndex> for BranchingTree
+impl<S: TrieStorage> IndexMut<NodeIndex> for BranchingTree<S>
 {
     fn index_mut(&mut self, i: NodeIndex) -> &mut Self::Output
     {
@@ -0,0 +1,122 @@
+use core::mem::MaybeUninit;
+use core::ops::{Deref, DerefMut};
+use core::{ptr, slice};
+use alloc::vec::Vec;
+
+/// The storage of the nodes (leaves and branching) of a trie
+pub(crate) trait TrieStorage {
+    type Vec<T>: NodeVec<T>;
+}
+
+/// A growable (or not) list of nodes
+pub(crate) trait NodeVec<T>: DerefMut<Target=[T]> {
+    fn with_capacity(capacity: usize) -> Self;
+    fn push(&mut self, t: T);
+    fn pop(&mut self) -> Option<T>;
+    fn swap_remove(&mut self, i: usize) -> T;
+    fn clear(&mut self);
+}
+
+/// Heap allocated storage (the default one)
+pub(crate) struct HeapStorage;
+
+impl TrieStorage for HeapStorage {
+    type Vec<T> = Vec<T>;
+}
+
+impl<T> NodeVec<T> for Vec<T> {
+    #[inline] fn with_capacity(capacity: usize) -> Self { Vec::with_capacity(capacity) }
+    #[inline] fn push(&mut self, t: T) { Vec::push(self, t) }
+    #[inline] fn pop(&mut self) -> Option<T> { Vec::pop(self) }
+    #[inline] fn swap_remove(&mut self, i: usize) -> T { Vec::swap_remove(self, i) }
+    #[inline] fn clear(&mut self) { Vec::clear(self) }
+}
+
+/// Fixed size storage, without any allocation
+///
+/// Each kind of node (leaf or branching) could not exceed `N`
+pub(crate) struct ArrayStorage<const N: usize>;
+
+impl<const N: usize> TrieStorage for ArrayStorage<N> {
+    type Vec<T> = ArrayVec<T,N>;
+}
+
+pub(crate) struct ArrayVec<T, const N: usize> {
+    len: usize,
+    items: [MaybeUninit<T>; N]
+}
+
+impl<T, const N: usize> ArrayVec<T,N> {
+    #[inline]
+    pub(crate) fn is_full(&self) -> bool { self.len == N }
+}
+
+impl<T, const N: usize> NodeVec<T> for ArrayVec<T,N>
+{
+    #[inline]
+    fn with_capacity(_: usize) -> Self {
+        // an array of uninitialized items needs no initialization
+        Self { len: 0, items: unsafe { MaybeUninit::<[MaybeUninit<T>; N]>::uninit().assume_init() } }
+    }
+
+    /// # Panics
+    /// Panics if the array is full.
+    #[inline]
+    fn push(&mut self, t: T) {
+        assert!( self.len < N, "fixed size trie storage is full");
+        self.items[self.len].write(t);
+        self.len += 1;
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            Some(unsafe { self.items[self.len].assume_init_read() })
+        }
+    }
+
+    #[inline]
+    fn swap_remove(&mut self, i: usize) -> T {
+        assert!( i < self.len );
+        self.items.swap(i, self.len-1);
+        self.pop().unwrap()
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        let len = self.len;
+        self.len = 0;
+        unsafe { ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.items.as_mut_ptr() as *mut T, len)) }
+    }
+}
+
+impl<T, const N: usize> Deref for ArrayVec<T,N> {
+    type Target = [T];
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        unsafe { slice::from_raw_parts(self.items.as_ptr() as *const T, self.len) }
+    }
+}
+
+impl<T, const N: usize> DerefMut for ArrayVec<T,N> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { slice::from_raw_parts_mut(self.items.as_mut_ptr() as *mut T, self.len) }
+    }
+}
+
+impl<T:Clone, const N: usize> Clone for ArrayVec<T,N> {
+    fn clone(&self) -> Self {
+        let mut array = Self::with_capacity(N);
+        self.iter().for_each(|t| array.push(t.clone()));
+        array
+    }
+}
+
+impl<T, const N: usize> Drop for ArrayVec<T,N> {
+    #[inline]
+    fn drop(&mut self) { self.clear() }
+}
@@ -1,11 +1,31 @@
-use std::net::Ipv6Addr;
-use ipnet::Ipv6Net;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::ops::RangeInclusive;
+use ipnet::{Ipv4Net, Ipv6Net};
 
 use rand::*;
 use rand::distributions::*;
 use crate::*;
 
 
+/// Random IPv4 prefixes (with a length in the given range) used as fixture by several tests
+fn random_ipv4_prefixes(n: usize, lens: RangeInclusive<u8>) -> impl Iterator<Item=Ipv4Prefix>
+{
+    let mut rng = thread_rng();
+    let prefix = Uniform::<u8>::from(lens);
+    let addr = Uniform::<u32>::from(0..=u32::MAX);
+    std::iter::repeat_with(move || {
+        Ipv4Prefix::new(addr.sample(&mut rng).into(), prefix.sample(&mut rng)).unwrap()
+    }).take(n)
+}
+
+/// Random IPv4 addresses used to compare the lookups of several tries
+fn random_ipv4_addrs(n: usize) -> impl Iterator<Item=Ipv4Addr>
+{
+    let mut rng = thread_rng();
+    std::iter::repeat_with(move || Ipv4Addr::from(rng.next_u32())).take(n)
+}
+
+
 #[test]
 fn ipv6_tries() {
 
@@ -33,4 +53,1330 @@ fn ipv6_tries() {
             assert!( p1.covers_equally(p2) );
             assert!( p2.covers_equally(p3) );
         });
-}
\ No newline at end of file
+}
+
+#[test]
+fn checked_compression() {
+
+    let samples = random_ipv4_prefixes(10_000, 8..=32).collect::<Vec<_>>();
+
+    let trie = Ipv4RTrieSet::from_iter(samples.iter().cloned());
+    let len = trie.len().get();
+    let lctrie = trie.clone().try_compress().unwrap();
+    assert_eq!( lctrie.0.check(), Ok(len));
+    assert!( samples.iter().all(|p| lctrie.contains(p)) );
+}
+
+
+#[test]
+fn duplicate_leaf_in_compressed_trie() {
+
+    let trie = Ipv4RTrieSet::from_iter(["10.0.0.0/8", "10.1.0.0/16", "10.1.1.0/24", "10.128.0.0/9",
+        "192.168.0.0/16", "192.168.1.0/24", "192.168.128.0/24", "1.1.1.0/24", "2.2.0.0/16"].map(|p| p.parse().unwrap()));
+    let mut lctrie = trie.try_compress().unwrap();
+    let nodes = lctrie.0.branching.iter().map(|(i,_)| i).collect::<Vec<_>>();
+    assert!( nodes.len() > 1);
+
+    // links a leaf child of a node as a child of another node
+    let tree = &lctrie.0.branching;
+    let (node, leaf) = nodes.iter()
+        .find_map(|&i| (0..tree[i].children())
+            .map(|n| *tree[i].child(n))
+            .find(|c| c.is_leaf() && *c != tree[i].escape)
+            .map(|c| (i, c)))
+        .unwrap();
+    let other = *nodes.iter().find(|&&i| i != node).unwrap();
+    *lctrie.0.branching[other].child_mut(0) = leaf;
+
+    let leaf = trie::common::LeafIndex::from(leaf).index();
+    assert_eq!( lctrie.0.check(), Err(CompressError::DuplicateLeaf(leaf)));
+}
+
+#[test]
+fn covering_and_covered_leaves() {
+
+    let trie = Ipv4RTrieSet::from_iter(random_ipv4_prefixes(5_000, 4..=24));
+    random_ipv4_prefixes(5_000, 4..=24)
+        .for_each(|p| {
+            let covering = trie.0.covering_leaves(&p).map(|l| trie.0[l]).collect::<Vec<_>>();
+            let mut expected = trie.iter().filter(|q| q.covers(&p)).cloned().collect::<Vec<_>>();
+            expected.sort_by_key(|q| std::cmp::Reverse(q.len()));
+            assert_eq!( covering, expected );
+
+            let mut covered = trie.0.covered_leaves(&p).into_iter().map(|l| trie.0[l]).collect::<Vec<_>>();
+            let mut expected = trie.iter().filter(|q| p.covers(*q)).cloned().collect::<Vec<_>>();
+            covered.sort_by_key(|q| (q.bitslot(), q.len()));
+            expected.sort_by_key(|q| (q.bitslot(), q.len()));
+            assert_eq!( covered, expected );
+        });
+}
+
+#[test]
+fn alloc_only_lookups() {
+    // only core and alloc items are used here (as in a no_std context)
+    use alloc::vec::Vec;
+    use core::net::Ipv4Addr;
+
+    let prefixes = [([10,0,0,0],8), ([10,1,0,0],16), ([10,1,2,0],24), ([192,168,0,0],16)]
+        .iter()
+        .map(|&(a,len)| Ipv4Prefix::new(Ipv4Addr::from(a), len).unwrap())
+        .collect::<Vec<_>>();
+    let mut trie = Ipv4RTrieMap::with_root(0u32);
+    prefixes.iter().enumerate().for_each(|(i,p)| { trie.insert(*p, i as u32 + 1); });
+    let lctrie = trie.compress();
+
+    assert_eq!( lctrie.lookup(&Ipv4Addr::new(10,1,2,3)), (&prefixes[2], &3));
+    assert_eq!( lctrie.lookup(&Ipv4Addr::new(10,1,3,3)), (&prefixes[1], &2));
+    assert_eq!( lctrie.lookup(&Ipv4Addr::new(10,2,0,1)), (&prefixes[0], &1));
+    assert_eq!( lctrie.lookup(&Ipv4Addr::new(192,168,1,1)), (&prefixes[3], &4));
+    assert_eq!( *lctrie.lookup(&Ipv4Addr::new(172,16,0,1)).1, 0);
+}
+
+#[test]
+fn extend_compressed_set() {
+
+    let first = random_ipv4_prefixes(5_000, 8..=32).collect::<Vec<_>>();
+    let second = random_ipv4_prefixes(5_000, 8..=32).collect::<Vec<_>>();
+
+    let mut lctrie = Ipv4LCTrieSet::from_iter(first.iter().cloned());
+    lctrie.extend(second.iter().cloned());
+    assert!( lctrie.0.check().is_ok() );
+
+    let expected = Ipv4RTrieSet::from_iter(first.into_iter().chain(second));
+    assert_eq!( lctrie.len(), expected.len());
+    random_ipv4_addrs(10_000)
+        .for_each(|a| assert_eq!( lctrie.lookup(&a), expected.lookup(&a)));
+}
+
+#[test]
+fn compression_with_exhausted_capacity() {
+
+    let trie = Ipv4RTrieSet::from_iter(random_ipv4_prefixes(50_000, 8..=32));
+    let lctrie = trie::lctrie::LevelCompressedTrie::with_capacity(trie.0.clone(), 0);
+    assert_eq!( lctrie.check(), Ok(trie.len().get()));
+    random_ipv4_addrs(10_000)
+        .for_each(|a| assert_eq!( lctrie.lookup(&a).0, trie.lookup(&a)));
+}
+
+#[test]
+fn preorder_visit() {
+
+    let samples = random_ipv4_prefixes(5_000, 4..=32);
+
+    let lctrie = Ipv4RTrieMap::from_iter(samples.map(|p| (p, p.len()))).compress();
+
+    let mut visited = Vec::new();
+    lctrie.for_each_preorder(|p,_| visited.push(*p));
+    assert_eq!( visited.len(), lctrie.len().get());
+
+    let mut expected = lctrie.iter().map(|(p,_)| *p).collect::<Vec<_>>();
+    expected.sort_by_key(|p| (p.bitslot_trunc(), p.len()));
+    let mut sorted = visited.clone();
+    sorted.sort_by_key(|p| (p.bitslot_trunc(), p.len()));
+    assert_eq!( sorted, expected);
+
+    visited.iter().enumerate()
+        .for_each(|(i,p)| assert!( !visited[i+1..].iter().any(|q| q.covers(p) && q != p)));
+}
+
+#[test]
+fn set_into_net() {
+
+    let trie = random_ipv4_prefixes(10_000, 8..=32).collect::<Ipv4RTrieSet>();
+
+    let nets = trie.clone().into_net();
+    assert_eq!( nets.len(), trie.len());
+    assert!( trie.iter().zip(nets.iter()).all(|(p,n)| p.to_string() == n.to_string()));
+    random_ipv4_addrs(10_000)
+        .for_each(|a| assert_eq!( trie.lookup(&a).to_string(), nets.lookup(&a).to_string()));
+}
+
+#[test]
+fn compressed_iteration_order() {
+
+    let samples = random_ipv4_prefixes(10_000, 8..=32).collect::<Vec<_>>();
+
+    let mut trie = Ipv4RTrieMap::from_iter(samples.iter().map(|p| (*p, p.len())));
+    samples.iter().step_by(7).for_each(|p| { trie.remove(p); });
+
+    // the compression keeps the insertion order (the only documented one)
+    let expected = trie.iter_insertion_order().map(|(p,v)| (*p,*v)).collect::<Vec<_>>();
+    let set = trie.prefixes();
+    let lctrie = trie.compress();
+    assert!( lctrie.iter().map(|(p,v)| (*p,*v)).eq(expected.iter().cloned()));
+    assert!( lctrie.prefixes().iter().eq(expected.iter().map(|(p,_)| p)));
+    assert!( set.clone().compress().iter().eq(set.iter_insertion_order()));
+}
+
+#[test]
+fn insertion_and_removal() {
+
+    let samples = random_ipv4_prefixes(5_000, 4..=24).collect::<Vec<_>>();
+
+    let mut trie = Ipv4RTrieSet::from_iter(samples.iter().cloned());
+    samples.iter().step_by(3).for_each(|p| { trie.remove(p); });
+    let remaining = samples.iter().enumerate()
+        .filter(|(i,_)| i % 3 != 0)
+        .map(|(_,p)| *p)
+        .filter(|p| !samples.iter().step_by(3).any(|q| q == p))
+        .collect::<Vec<_>>();
+
+    assert!( trie.0.branching.0.iter()
+        .all(|b| b.escape.index() < trie.len().get()
+            && b.child.iter().all(|c| c.is_branching() || trie::common::LeafIndex::from(*c).index() < trie.len().get())));
+    assert!( remaining.iter().all(|p| trie.contains(p)));
+    assert!( samples.iter().step_by(3).all(|p| !trie.contains(p)));
+
+    random_ipv4_addrs(10_000)
+        .for_each(|a| {
+            let expected = remaining.iter().filter(|p| p.covers(&a)).max_by_key(|p| p.len())
+                .cloned().unwrap_or(Ipv4Prefix::root());
+            assert_eq!( *trie.lookup(&a), expected);
+        });
+}
+
+#[test]
+fn escape_leaf_moved_by_removal() {
+
+    // 10.0.0.0/8 is inserted last and is the escape of several branching
+    // which are not on the lookup path of its own prefix: when another leaf
+    // is removed, it is moved at its place and all of them should follow
+    let prefixes = ["1.0.0.0/8", "10.1.0.0/16", "10.2.0.0/16", "10.1.1.0/24", "10.1.2.0/24", "10.2.1.0/24", "10.0.0.0/8"]
+        .map(|p| p.parse::<Ipv4Prefix>().unwrap());
+    let mut trie = Ipv4RTrieSet::from_iter(prefixes);
+    assert!( trie.remove(&prefixes[0]) );
+    assert_eq!( trie.check_invariants(), Ok(()));
+
+    prefixes[1..].iter().for_each(|p| assert_eq!( trie.lookup(&p.network()), p));
+    ["10.0.0.1", "10.3.0.1", "10.200.0.1"].iter()
+        .map(|a| a.parse::<Ipv4Addr>().unwrap())
+        .for_each(|a| assert_eq!( trie.lookup(&a), &prefixes[6]));
+    assert_eq!( trie.lookup(&"1.1.1.1".parse::<Ipv4Addr>().unwrap()), &Ipv4Prefix::root());
+}
+
+#[test]
+fn array_set() {
+
+    let prefixes = ["10.0.0.0/8", "10.1.0.0/16", "10.1.1.0/24", "10.128.0.0/9", "192.168.0.0/16", "192.168.1.0/24", "1.1.1.1/32"]
+        .iter()
+        .map(|p| p.parse::<Ipv4Prefix>().unwrap())
+        .collect::<Vec<_>>();
+
+    let trie = Ipv4RTrieSet::from_iter(prefixes.iter().cloned());
+    let mut array = ArrayRTrieSet::<Ipv4Prefix,8>::new();
+    prefixes.iter().for_each(|p| assert_eq!( array.insert(*p), Ok(true)));
+    assert_eq!( array.insert("2.0.0.0/8".parse().unwrap()), Err(CapacityError));
+    assert_eq!( array.insert(prefixes[3]), Ok(false));
+
+    assert_eq!( array.len(), trie.len());
+    assert!( array.iter().eq(trie.iter()));
+
+    random_ipv4_addrs(10_000)
+        .chain(prefixes.iter().map(|p| p.network()))
+        .for_each(|a| assert_eq!( array.lookup(&a), trie.lookup(&a)));
+
+    assert!( array.remove(&prefixes[1]) );
+    assert!( !array.contains(&prefixes[1]) );
+    assert_eq!( array.lookup(&Ipv4Addr::new(10,1,2,3)), &prefixes[0]);
+}
+
+#[test]
+fn one_matching_leaf_in_second_slot() {
+
+    // the only non escape leaf of the root branching is in slot 1
+    let p = "128.0.0.0/1".parse::<Ipv4Prefix>().unwrap();
+    let trie = Ipv4RTrieSet::from_iter([p]);
+    let root = &trie.0.branching[trie::common::BranchingIndex::root()];
+    assert!( root.child[0].is_leaf() && trie::common::LeafIndex::from(root.child[0]).is_root_leaf());
+
+    let l = trie.0.branching.search_one_matching_leaf(trie::common::BranchingIndex::root());
+    assert_eq!( trie.0[l], p);
+}
+
+#[test]
+fn random_operations_keep_invariants() {
+
+    let mut rng = thread_rng();
+
+    // short prefixes to get many interactions between insertions and removals
+    let mut samples = random_ipv4_prefixes(5_000, 1..=12);
+    let mut trie = Ipv4RTrieSet::new();
+    let mut inserted = Vec::new();
+    for _ in 0..5_000 {
+        if inserted.is_empty() || rng.gen_bool(0.6) {
+            let p = samples.next().unwrap();
+            if trie.insert(p) { inserted.push(p); }
+        } else {
+            let p = inserted.swap_remove(rng.gen_range(0..inserted.len()));
+            assert!( trie.remove(&p) );
+        }
+        assert_eq!( trie.check_invariants(), Ok(()));
+    }
+    assert_eq!( trie.len().get(), inserted.len() + 1);
+    assert!( inserted.iter().all(|p| trie.contains(p)));
+}
+
+#[test]
+fn insertion_after_removals() {
+
+    // removals leave some branching with no relevant leaf below them
+    let sequences = [
+        ["+130.208.0.0/12", "+144.0.0.0/5", "-130.208.0.0/12", "+128.0.0.0/2", "+162.0.0.0/8"].as_slice(),
+        ["+165.0.0.0/8", "+176.0.0.0/4", "-176.0.0.0/4", "+248.0.0.0/6", "+128.0.0.0/1"].as_slice(),
+        ["+146.160.0.0/11", "+179.96.0.0/12", "+189.0.0.0/8", "-189.0.0.0/8", "-179.96.0.0/12", "+240.0.0.0/5", "+164.0.0.0/6"].as_slice(),
+    ];
+    sequences.iter().for_each(|ops| {
+        let mut trie = Ipv4RTrieSet::new();
+        let mut inserted = Vec::new();
+        ops.iter().for_each(|op| {
+            let p = op[1..].parse::<Ipv4Prefix>().unwrap();
+            if op.starts_with('+') {
+                assert!( trie.insert(p) );
+                inserted.push(p);
+            } else {
+                assert!( trie.remove(&p) );
+                inserted.retain(|q| *q != p);
+            }
+            assert_eq!( trie.check_invariants(), Ok(()));
+            inserted.iter().for_each(|p| assert_eq!( trie.lookup(&p.network()), p));
+        });
+    });
+}
+
+#[test]
+fn escape_prefix_above_remaining_branching() {
+
+    // the removal of 0.0.0.0/2 keeps a branching which is deeper than
+    // the added 0.0.0.0/1: this one should become an escape leaf above it,
+    // not a child of it
+    let prefixes = ["76.0.0.0/6", "0.0.0.0/2", "0.0.0.0/1"].map(|p| p.parse::<Ipv4Prefix>().unwrap());
+    let mut trie = Ipv4RTrieSet::from_iter(prefixes[..2].iter().cloned());
+    assert!( trie.remove(&prefixes[1]) );
+    assert!( trie.insert(prefixes[2]) );
+    assert_eq!( trie.check_invariants(), Ok(()));
+
+    assert_eq!( trie.lookup(&prefixes[0]), &prefixes[0]);
+    assert_eq!( trie.lookup(&prefixes[2]), &prefixes[2]);
+    assert_eq!( trie.lookup(&"64.0.0.1".parse::<Ipv4Addr>().unwrap()), &prefixes[2]);
+    assert_eq!( trie.lookup(&"128.0.0.1".parse::<Ipv4Addr>().unwrap()), &Ipv4Prefix::root());
+}
+
+#[test]
+fn removal_of_the_last_leaf() {
+
+    // the removed leaf is the last one, so no other leaf is moved at its place
+    let prefixes = ["10.0.0.0/8", "10.1.0.0/16", "120.0.0.0/5"].map(|p| p.parse::<Ipv4Prefix>().unwrap());
+    let mut trie = Ipv4RTrieSet::new();
+    assert!( trie.insert(prefixes[2]) );
+    assert!( trie.remove(&prefixes[2]) );
+    assert_eq!( trie.check_invariants(), Ok(()));
+    assert_eq!( trie.len().get(), 1);
+
+    let mut trie = Ipv4RTrieSet::from_iter(prefixes);
+    assert!( trie.remove(&prefixes[2]) );
+    assert_eq!( trie.check_invariants(), Ok(()));
+    prefixes[..2].iter().for_each(|p| assert_eq!( trie.lookup(p), p));
+    assert_eq!( trie.lookup(&prefixes[2]), &Ipv4Prefix::root());
+}
+
+#[test]
+fn estimated_compressed_size() {
+
+    let samples = random_ipv4_prefixes(10_000, 8..=32).collect::<Vec<_>>();
+    let mut trie = samples.iter().cloned().collect::<Ipv4RTrieSet>();
+
+    // the estimation shares the sizing pass of the compression, so it is exact
+    let estimated = trie.estimated_compressed_bytes();
+    assert_eq!( trie.clone().compress().memory_bytes(), estimated);
+
+    samples.iter().step_by(3).for_each(|p| { trie.remove(p); });
+    let estimated = trie.estimated_compressed_bytes();
+    assert_eq!( trie.compress().memory_bytes(), estimated);
+}
+
+#[test]
+fn text_tree() {
+
+    let trie = ["10.0.0.0/8", "10.128.0.0/9", "10.1.0.0/16", "10.1.2.0/24", "1.1.1.0/24", "10.0.0.0/16"]
+        .iter()
+        .map(|p| p.parse::<Ipv4Prefix>().unwrap())
+        .collect::<Ipv4RTrieSet>();
+
+    let mut out = Vec::new();
+    trie.write_tree(&mut out).unwrap();
+    assert_eq!( String::from_utf8(out).unwrap(),
+                "0.0.0.0/0\n  1.1.1.0/24\n  10.0.0.0/8\n    10.0.0.0/16\n    10.1.0.0/16\n      10.1.2.0/24\n    10.128.0.0/9\n");
+}
+
+#[test]
+fn ipv6_net_prefix_lookup_by_address() {
+
+    let trie = ["2001:db8::/32", "2001:db8:1:2::/64", "2001:db8:1::/48"]
+        .iter()
+        .map(|p| p.parse::<Ipv6NetPrefix>().unwrap())
+        .collect::<RTrieSet<Ipv6NetPrefix>>();
+
+    let addr = "2001:db8:1:2::1".parse::<Ipv6Addr>().unwrap();
+    assert_eq!( trie.lookup(&addr).to_string(), "2001:db8:1:2::/64");
+    let addr = "2001:db8:1:3::1".parse::<Ipv6Addr>().unwrap();
+    assert_eq!( trie.lookup(&addr).to_string(), "2001:db8:1::/48");
+    let addr = "2001:db9::1".parse::<Ipv6Addr>().unwrap();
+    assert_eq!( trie.lookup(&addr).to_string(), "::/0");
+    assert_eq!( trie.compress().lookup(&addr).to_string(), "::/0");
+}
+
+#[test]
+fn next_free_block() {
+
+    let trie = ["10.0.0.0/16", "10.1.0.0/24", "10.1.1.0/24", "10.1.3.5/32", "10.1.4.0/22", "10.1.16.0/20"]
+        .iter()
+        .map(|p| p.parse::<Ipv4Prefix>().unwrap())
+        .collect::<Ipv4RTrieSet>();
+    let next = |after: &str, len| trie.next_free(after.parse().unwrap(), len).map(|p| p.to_string());
+
+    assert_eq!( next("10.0.0.0/24", 24).as_deref(), Some("10.1.2.0/24"));
+    assert_eq!( next("10.1.2.0/24", 24).as_deref(), Some("10.1.8.0/24"));
+    assert_eq!( next("10.1.2.0/24", 30).as_deref(), Some("10.1.3.0/30"));
+    assert_eq!( next("10.1.3.0/30", 30).as_deref(), Some("10.1.3.8/30"));
+    assert_eq!( next("10.0.0.0/8", 24).as_deref(), Some("11.0.0.0/24"));
+    assert_eq!( next("10.1.0.0/16", 20).as_deref(), Some("10.2.0.0/20"));
+    assert_eq!( next("255.255.255.0/24", 24), None);
+    assert_eq!( next("1.0.0.0/8", 33), None);
+}
+
+#[test]
+fn for_loops_on_references() {
+
+    let mut set = Ipv4RTrieSet::new();
+    let mut map = Ipv4RTrieMap::new();
+    ["10.0.0.0/8", "10.1.0.0/16", "1.1.1.0/24"].iter()
+        .map(|p| p.parse::<Ipv4Prefix>().unwrap())
+        .for_each(|p| { set.insert(p); map.insert(p, p.len()); });
+
+    let mut count = 0;
+    for p in &set {
+        assert!( map.get(p).is_some() );
+        count += 1;
+    }
+    assert_eq!( count, set.len().get());
+
+    for (_,v) in &mut map { *v += 1; }
+    for (k,v) in &map { assert_eq!( *v, k.len() + 1); }
+
+    let lcset = set.compress();
+    assert!( (&lcset).into_iter().eq(lcset.iter()));
+    let mut lcmap = map.compress();
+    for (_,v) in &mut lcmap { *v = 0; }
+    assert!( (&lcmap).into_iter().all(|(_,v)| *v == 0));
+}
+
+#[test]
+fn coverage_subset() {
+
+    let set = |prefixes: &[&str]| prefixes.iter()
+        .map(|p| p.parse::<Ipv4Prefix>().unwrap())
+        .collect::<Ipv4RTrieSet>();
+
+    let a = set(&["10.1.0.0/16", "10.200.1.0/24"]);
+    let b = set(&["10.0.0.0/9", "10.128.0.0/9"]);
+    assert!( a.is_covered_by(&b));
+    assert!( !b.is_covered_by(&a));
+    assert!( set(&["10.0.0.0/8"]).is_covered_by(&b));
+    assert!( !set(&["10.0.0.0/8", "11.0.0.0/24"]).is_covered_by(&b));
+    assert!( Ipv4RTrieSet::new().is_covered_by(&a));
+}
+
+#[test]
+fn membership_subset() {
+
+    let prefixes = ["10.0.0.0/8", "10.1.0.0/16", "1.1.1.0/24"]
+        .iter()
+        .map(|p| p.parse::<Ipv4Prefix>().unwrap())
+        .collect::<Vec<_>>();
+    let a = Ipv4RTrieSet::from_iter(prefixes.iter().cloned());
+    let b = Ipv4RTrieSet::from_iter(prefixes.iter().skip(1).cloned());
+
+    assert!( b.is_subset(&a) && a.is_superset(&b));
+    assert!( !a.is_subset(&b) && !b.is_superset(&a));
+    assert!( a.is_subset(&a) && a.is_superset(&a));
+    // being covered is not enough
+    let c = Ipv4RTrieSet::from(["10.1.1.0/24".parse().unwrap()]);
+    assert!( c.is_covered_by(&a) && !c.is_subset(&a));
+}
+
+#[test]
+fn sorted_map_entries() {
+
+    let map = random_ipv4_prefixes(1_000, 1..=32).map(|p| (p, p.len())).collect::<Ipv4RTrieMap<_>>();
+    let len = map.len().get();
+
+    let entries = map.into_sorted_vec();
+    assert_eq!( entries.len(), len);
+    assert!( entries.windows(2).all(|w| {
+        let (a, b) = (w[0].0, w[1].0);
+        (u32::from(a.network()), a.len()) < (u32::from(b.network()), b.len())
+    }));
+    assert!( entries.iter().all(|(p,v)| p.len() == *v));
+}
+
+#[test]
+fn bounding_prefix() {
+
+    let set = |prefixes: &[&str]| prefixes.iter()
+        .map(|p| p.parse::<Ipv4Prefix>().unwrap())
+        .collect::<Ipv4RTrieSet>();
+
+    assert_eq!( set(&["10.1.0.0/16", "10.2.0.0/16"]).bounding_prefix().to_string(), "10.0.0.0/14");
+    assert_eq!( set(&["10.1.0.0/16", "10.1.2.0/24"]).bounding_prefix().to_string(), "10.1.0.0/16");
+    assert_eq!( set(&["10.1.2.3/32"]).bounding_prefix().to_string(), "10.1.2.3/32");
+    assert_eq!( set(&["10.1.0.0/16", "10.1.2.0/24", "10.128.0.0/9"]).bounding_prefix().to_string(), "10.0.0.0/8");
+    assert_eq!( set(&[]).bounding_prefix(), Ipv4Prefix::root());
+}
+
+#[test]
+fn coverage_ratio() {
+
+    let trie = ["10.0.0.0/9", "10.0.1.0/24", "10.128.0.0/12", "10.130.0.0/16", "192.168.0.0/16"]
+        .iter()
+        .map(|p| p.parse::<Ipv4Prefix>().unwrap())
+        .collect::<Ipv4RTrieSet>();
+    let ratio = |space: &str| trie.coverage_ratio(space.parse().unwrap());
+
+    assert_eq!( ratio("10.0.0.0/8"), 0.5 + 1./16.);
+    assert_eq!( ratio("10.128.0.0/9"), 1./8.);
+    assert_eq!( ratio("10.0.0.0/9"), 1.);
+    assert_eq!( ratio("0.0.0.0/0"), (1./512.) + (1./4096.) + (1./65536.));
+    assert_eq!( ratio("172.16.0.0/12"), 0.);
+
+    let trie = Ipv6RTrieSet::from(["2001:db8::/33".parse().unwrap()]);
+    assert_eq!( trie.coverage_ratio("2001:db8::/32".parse().unwrap()), 0.5);
+    assert_eq!( trie.coverage_ratio(Ipv6Prefix::root()), 2f64.powi(-33));
+}
+
+#[test]
+fn restrict_to_space() {
+
+    let samples = random_ipv4_prefixes(1_000, 4..=32)
+        .chain(["10.0.0.0/8", "10.1.0.0/16", "192.168.1.0/24", "192.168.0.0/16"].map(|p| p.parse().unwrap()))
+        .collect::<Vec<_>>();
+
+    let space = "10.0.0.0/8".parse::<Ipv4Prefix>().unwrap();
+    let mut trie = Ipv4RTrieSet::from_iter(samples.iter().cloned());
+    trie.restrict_to(space);
+
+    assert!( trie.iter().skip(1).all(|p| space.covers(p)));
+    assert!( samples.iter().filter(|p| space.covers(*p)).all(|p| trie.contains(p)));
+    assert!( !trie.contains(&"192.168.1.0/24".parse::<Ipv4Prefix>().unwrap()));
+    assert_eq!( trie.check_invariants(), Ok(()));
+
+    // the same as a removal of the uncovered prefixes one by one
+    ["0.0.0.0/0", "0.0.0.0/1", "192.168.0.0/16", "192.168.1.0/24", "10.1.1.1/32"].iter()
+        .map(|p| p.parse::<Ipv4Prefix>().unwrap())
+        .chain(samples.iter().step_by(100).cloned())
+        .for_each(|space| {
+            let mut trie = Ipv4RTrieSet::from_iter(samples.iter().cloned());
+            let mut expected = trie.clone();
+            samples.iter().filter(|p| !space.covers(*p)).for_each(|p| { expected.remove(p); });
+            trie.restrict_to(space);
+            assert_eq!( trie.check_invariants(), Ok(()));
+            assert_eq!( trie.len(), expected.len());
+            assert!( expected.iter().all(|p| trie.contains(p)));
+        });
+}
+
+#[test]
+fn index_overflow()
+{
+    use crate::trie::common::{check_index, INDEX_MAX};
+    assert_eq!( check_index(0), Ok(()));
+    assert_eq!( check_index(INDEX_MAX), Ok(()));
+    assert_eq!( check_index(INDEX_MAX+1), Err(CapacityError));
+
+    let mut trie = Ipv4RTrieSet::new();
+    assert_eq!( trie.try_insert("10.0.0.0/8".parse().unwrap()), Ok(true));
+    assert_eq!( trie.try_insert("10.0.0.0/8".parse().unwrap()), Ok(false));
+    assert_eq!( trie.len().get(), 2);
+}
+
+#[test]
+fn explode_to_length()
+{
+    let trie = Ipv4RTrieSet::from(["10.0.0.0/22".parse().unwrap()]);
+    let exploded = trie.explode_to(24);
+    assert_eq!( exploded.iter().skip(1).map(|p| p.to_string()).collect::<Vec<_>>(),
+                ["10.0.0.0/24", "10.0.1.0/24", "10.0.2.0/24", "10.0.3.0/24"]);
+
+    let trie = Ipv4RTrieSet::from(["10.0.0.0/22".parse().unwrap(), "10.0.1.0/24".parse().unwrap(), "10.0.1.1/32".parse().unwrap()]);
+    assert_eq!( trie.explode_to(24).len().get(), 5);
+    assert_eq!( trie.explode_to(8).iter().map(|p| p.to_string()).collect::<Vec<_>>(), ["0.0.0.0/0", "10.0.0.0/8"]);
+    assert!( trie.explode_to(0).is_empty());
+}
+
+#[test]
+fn mac_prefixes()
+{
+    let oui = |s: &str| s.parse::<MacPrefix>().unwrap();
+    let mac = |s: &str| s.parse::<MacAddr>().unwrap();
+
+    let mut trie = RTrieMap::with_root("unknown");
+    trie.insert(oui("00:1a:2b:00:00:00/24"), "vendor A");
+    trie.insert(oui("00:1a:2c:00:00:00/24"), "vendor B");
+    trie.insert(oui("00:1a:2b:3c:40:00/36"), "vendor A, product 1");
+    trie.insert(oui("00:1a:2b:3c:4d:5e"), "vendor A, device");
+
+    assert_eq!( *trie.lookup(&mac("00:1a:2b:00:00:01")).1, "vendor A");
+    assert_eq!( *trie.lookup(&mac("00:1a:2b:3c:4f:ff")).1, "vendor A, product 1");
+    assert_eq!( *trie.lookup(&mac("00:1a:2b:3c:4d:5e")).1, "vendor A, device");
+    assert_eq!( *trie.lookup(&mac("00:1a:2c:ff:ff:ff")).1, "vendor B");
+    assert_eq!( *trie.lookup(&mac("00:1a:2d:00:00:00")).1, "unknown");
+
+    let lctrie = trie.compress();
+    assert_eq!( *lctrie.lookup(&mac("00:1a:2b:3c:4f:ff")).1, "vendor A, product 1");
+    assert_eq!( *lctrie.lookup(&mac("00:1a:2d:00:00:00")).1, "unknown");
+
+    assert_eq!( oui("00:1a:2b:3c:40:00/36").to_string(), "00:1a:2b:3c:40:00/36");
+    assert_eq!( "00:1a:2b:00:00:00/49".parse::<MacPrefix>(), Err(IpPrefixError::PrefixLenError));
+    assert_eq!( "00:1a:2b:00:00".parse::<MacAddr>(), Err(IpPrefixError::AddrParseError));
+    assert!( oui("02:00:00:00:00:00/8").is_private() );
+    assert!( !oui("00:1a:2b:00:00:00/24").is_private() );
+}
+
+#[test]
+fn overlapping_prefixes()
+{
+    let trie = Ipv4RTrieSet::from_iter(["10.1.0.0/16", "10.1.2.0/24", "192.168.0.0/24"].map(|p| p.parse().unwrap()));
+    let overlaps = |s: &str| trie.overlaps_any(&s.parse::<Ipv4Prefix>().unwrap());
+
+    // covered by a stored prefix
+    assert!( overlaps("10.1.3.0/24"));
+    assert!( overlaps("10.1.0.0/16"));
+    assert!( overlaps("192.168.0.1/32"));
+    // covering a stored prefix
+    assert!( overlaps("10.0.0.0/8"));
+    assert!( overlaps("192.168.0.0/16"));
+    assert!( overlaps("0.0.0.0/0"));
+    // disjoint
+    assert!( !overlaps("10.2.0.0/16"));
+    assert!( !overlaps("192.168.1.0/24"));
+    assert!( !overlaps("11.0.0.0/8"));
+    assert!( !Ipv4RTrieSet::new().overlaps_any(&"0.0.0.0/0".parse::<Ipv4Prefix>().unwrap()));
+
+    let net = trie.iter().copied().map(Ipv4Net::from).collect::<RTrieSet<_>>();
+    assert!( trie.overlaps_any(&"10.0.0.1/8".parse::<Ipv4Net>().unwrap()));
+    assert!( !net.overlaps_any(&"10.2.0.0/16".parse::<Ipv4Prefix>().unwrap()));
+}
+
+#[test]
+fn owned_lookups()
+{
+    let trie = Ipv4RTrieSet::from_iter(["10.0.0.0/8", "10.1.0.0/16", "10.1.1.0/24"].map(|p| p.parse().unwrap()));
+    let lctrie = trie.clone().compress();
+
+    // addresses from 9.0.0.0 to 10.255.255.255
+    random_ipv4_addrs(1000)
+        .map(|a| Ipv4Addr::from(0x0900_0000 | (u32::from(a) & 0x01ff_ffff)))
+        .for_each(|a| {
+            assert_eq!( trie.lookup_owned(&a), *trie.lookup(&a));
+            assert_eq!( lctrie.lookup_owned(&a), *lctrie.lookup(&a));
+        });
+}
+
+#[test]
+fn compression_report()
+{
+    let trie = random_ipv4_prefixes(10_000, 8..=32).collect::<Ipv4RTrieSet>();
+    let patricia = trie.0.branching.0.len();
+
+    let lctrie = trie.compress();
+    let report = lctrie.compression_report();
+    assert_eq!( report.nodes, lctrie.0.branching.iter().count());
+    assert_eq!( report.sizes.iter().sum::<usize>(), report.nodes);
+    assert_eq!( report.patricia_nodes, patricia);
+    assert!( report.ratio() > 0. && report.ratio() < 1.);
+}
+
+#[test]
+fn prefixes_with_parent()
+{
+    let trie = Ipv4RTrieSet::from_iter(["10.1.0.0/16", "10.1.2.0/24", "10.1.2.128/25", "10.1.3.0/24", "10.2.0.0/16", "192.168.0.0/16"].map(|p| p.parse().unwrap()));
+    let parents = trie.iter_with_parent()
+        .map(|(p, parent)| (p.to_string(), parent.map(|p| p.to_string())))
+        .collect::<std::collections::HashMap<_,_>>();
+
+    assert_eq!( parents.len(), 6);
+    assert_eq!( parents["10.1.0.0/16"], None);
+    assert_eq!( parents["10.1.2.0/24"].as_deref(), Some("10.1.0.0/16"));
+    assert_eq!( parents["10.1.2.128/25"].as_deref(), Some("10.1.2.0/24"));
+    assert_eq!( parents["10.1.3.0/24"].as_deref(), Some("10.1.0.0/16"));
+    assert_eq!( parents["10.2.0.0/16"], None);
+    assert_eq!( parents["192.168.0.0/16"], None);
+
+    // the parent is the one returned by the lookup of the prefix without it
+    trie.iter_with_parent()
+        .for_each(|(p, parent)| {
+            let mut other = trie.clone();
+            other.remove(p);
+            assert_eq!( parent, other.nearest(p).and_then(|q| trie.get(q)));
+        });
+}
+
+#[test]
+fn insertion_modes()
+{
+    let net = |s: &str| s.parse::<Ipv4Net>().unwrap();
+    let stored = |trie: &RTrieSet<Ipv4Net>| trie.get(&net("10.1.0.0/20")).map(|p| p.to_string());
+
+    let mut trie = RTrieSet::new();
+    assert!( trie.insert_mode(net("10.1.1.1/20"), InsertMode::KeepExisting));
+    assert!( !trie.insert_mode(net("10.1.2.2/20"), InsertMode::KeepExisting));
+    assert_eq!( stored(&trie).as_deref(), Some("10.1.1.1/20"));
+
+    let mut trie = RTrieSet::new();
+    assert!( trie.insert_mode(net("10.1.1.1/20"), InsertMode::Overwrite));
+    assert!( !trie.insert_mode(net("10.1.2.2/20"), InsertMode::Overwrite));
+    assert_eq!( stored(&trie).as_deref(), Some("10.1.2.2/20"));
+    assert_eq!( trie.len().get(), 2);
+}
+
+#[test]
+fn parent_chains_after_insertions_and_removals()
+{
+    let prefixes = ["10.0.0.0/8", "10.1.0.0/16", "10.1.1.0/24", "10.2.0.0/16", "192.168.0.0/16", "192.168.1.0/24", "172.16.0.0/12"]
+        .map(|p| p.parse::<Ipv4Prefix>().unwrap());
+    let mut trie = Ipv4RTrieSet::new();
+    prefixes.iter().for_each(|p| { trie.insert(*p); trie.0.debug_validate(); });
+    [1, 0, 5, 3].iter().for_each(|&i| { assert!(trie.remove(&prefixes[i])); trie.0.debug_validate(); });
+    prefixes.iter().for_each(|p| { trie.insert(*p); trie.0.debug_validate(); });
+    assert_eq!( trie.check_invariants(), Ok(()));
+}
+
+#[test]
+fn array_insertions_after_removals()
+{
+    // the insertion in a fixed size set goes through the subtree search of the
+    // insertions after removals, which should work without any allocation
+    let prefixes = ["10.0.0.0/8", "10.1.0.0/16", "10.1.1.0/24", "10.2.0.0/16", "192.168.0.0/16", "192.168.1.0/24", "172.16.0.0/12"]
+        .map(|p| p.parse::<Ipv4Prefix>().unwrap());
+    let mut array = ArrayRTrieSet::<Ipv4Prefix,16>::new();
+    let mut trie = Ipv4RTrieSet::new();
+    prefixes.iter().for_each(|p| { assert_eq!( array.insert(*p), Ok(true)); trie.insert(*p); });
+    [1, 0, 5, 3].iter().for_each(|&i| { assert!( array.remove(&prefixes[i])); trie.remove(&prefixes[i]); });
+    assert_eq!( array.0.check_invariants(), Ok(()));
+    [3, 0, 1, 5].iter().for_each(|&i| { assert_eq!( array.insert(prefixes[i]), Ok(true)); trie.insert(prefixes[i]); });
+    array.0.debug_validate();
+    assert_eq!( array.0.check_invariants(), Ok(()));
+
+    assert_eq!( array.len(), trie.len());
+    random_ipv4_addrs(10_000)
+        .chain(prefixes.iter().map(|p| p.network()))
+        .for_each(|a| assert_eq!( array.lookup(&a), trie.lookup(&a)));
+}
+
+#[test]
+#[should_panic(expected = "does not climb to the root")]
+fn parent_chain_cycle()
+{
+    let mut trie = Ipv4RTrieSet::from_iter(["10.0.0.0/8", "10.1.0.0/16", "10.2.0.0/16"].map(|p| p.parse().unwrap()));
+    let last = trie.0.branching.0.len() - 1;
+    trie.0.branching.0[last].parent = last.into();
+    trie.0.debug_validate();
+}
+
+#[test]
+fn floor_and_ceiling()
+{
+    let pfx = |s: &str| s.parse::<Ipv4Prefix>().unwrap();
+    let trie = Ipv4RTrieSet::from([pfx("10.0.0.0/24"), pfx("10.0.2.0/24")]);
+
+    assert_eq!( trie.floor(&pfx("10.0.1.0/24")), Some(&pfx("10.0.0.0/24")));
+    assert_eq!( trie.ceiling(&pfx("10.0.1.0/24")), Some(&pfx("10.0.2.0/24")));
+    assert_eq!( trie.floor(&pfx("10.0.2.0/24")), Some(&pfx("10.0.2.0/24")));
+    assert_eq!( trie.ceiling(&pfx("10.0.2.0/24")), Some(&pfx("10.0.2.0/24")));
+    assert_eq!( trie.floor(&pfx("10.0.0.0/16")), None);
+    assert_eq!( trie.ceiling(&pfx("10.0.0.0/16")), Some(&pfx("10.0.0.0/24")));
+    assert_eq!( trie.floor(&pfx("11.0.0.0/8")), Some(&pfx("10.0.2.0/24")));
+    assert_eq!( trie.ceiling(&pfx("11.0.0.0/8")), None);
+    // a longer prefix comes after the shorter one with the same address
+    assert_eq!( trie.floor(&pfx("10.0.0.128/25")), Some(&pfx("10.0.0.0/24")));
+    assert_eq!( trie.ceiling(&"10.0.0.1/25".parse::<Ipv4Net>().unwrap()), Some(&pfx("10.0.2.0/24")));
+    assert_eq!( Ipv4RTrieSet::new().floor(&pfx("10.0.0.0/8")), None);
+}
+
+#[test]
+fn coverage_depth()
+{
+    let trie = Ipv4RTrieSet::from_iter(["10.0.0.0/8", "10.1.0.0/16", "10.1.1.0/24", "10.1.1.128/25", "10.2.0.0/16"].map(|p| p.parse().unwrap()));
+
+    assert_eq!( trie.coverage_depth(&Ipv4Addr::new(10,1,1,1)), 3);
+    assert_eq!( trie.coverage_depth(&Ipv4Addr::new(10,1,1,200)), 4);
+    assert_eq!( trie.coverage_depth(&Ipv4Addr::new(10,2,1,1)), 2);
+    assert_eq!( trie.coverage_depth(&Ipv4Addr::new(11,1,1,1)), 0);
+    assert_eq!( trie.coverage_depth(&"10.1.0.0/16".parse::<Ipv4Prefix>().unwrap()), 2);
+    assert_eq!( trie.coverage_depth(&"10.0.0.0/7".parse::<Ipv4Prefix>().unwrap()), 0);
+}
+
+#[test]
+fn thread_safety()
+{
+    fn send_sync<T: Send + Sync>() {}
+    send_sync::<LCTrieSet<Ipv4Prefix>>();
+    send_sync::<LCTrieSet<Ipv6NetPrefix>>();
+    send_sync::<LCTrieMap<Ipv6Prefix, String>>();
+    send_sync::<RTrieSet<Ipv4Prefix>>();
+    send_sync::<RTrieMap<Ipv4Prefix, String>>();
+
+    let trie = std::sync::Arc::new(Ipv4RTrieSet::from(["10.0.0.0/8".parse().unwrap()]).compress());
+    let found = std::thread::spawn({
+        let trie = trie.clone();
+        move || *trie.lookup(&Ipv4Addr::new(10,1,1,1))
+    }).join().unwrap();
+    assert_eq!( &found, trie.lookup(&Ipv4Addr::new(10,2,2,2)));
+}
+
+#[test]
+fn checked_lookups()
+{
+    let trie = Ipv6RTrieSet::from(["2001:db8::/32".parse().unwrap()]);
+    let lctrie = trie.clone().compress();
+
+    let inside = "2001:db8::1".parse::<Ipv6Addr>().unwrap();
+    let outside = "2001:db9::1".parse::<Ipv6Addr>().unwrap();
+    assert_eq!( trie.lookup_checked(&inside), (trie.lookup(&inside), true));
+    assert_eq!( trie.lookup_checked(&outside), (&Ipv6Prefix::root(), false));
+    assert_eq!( lctrie.lookup_checked(&inside), (trie.lookup(&inside), true));
+    assert_eq!( lctrie.lookup_checked(&outside), (&Ipv6Prefix::root(), false));
+}
+
+#[test]
+fn maximal_prefixes()
+{
+    let trie = Ipv4RTrieSet::from(["10.0.0.0/8".parse().unwrap(), "10.0.0.0/24".parse().unwrap()]);
+    assert_eq!( trie.maximal().map(|p| p.to_string()).collect::<Vec<_>>(), ["10.0.0.0/24"]);
+
+    let trie = Ipv4RTrieSet::from_iter(["10.0.0.0/8", "10.1.0.0/16", "10.1.1.0/24", "10.2.0.0/16", "11.0.0.0/8", "192.168.0.0/16", "192.168.0.0/24"]
+        .map(|p| p.parse().unwrap()));
+    assert_eq!( trie.maximal().map(|p| p.to_string()).collect::<Vec<_>>(), ["10.1.1.0/24", "10.2.0.0/16", "11.0.0.0/8", "192.168.0.0/24"]);
+
+    assert_eq!( Ipv4RTrieSet::new().maximal().count(), 0);
+}
+
+#[test]
+fn minimal_prefixes()
+{
+    let trie = Ipv4RTrieSet::from(["10.0.0.0/8".parse().unwrap(), "10.0.0.0/24".parse().unwrap()]);
+    assert_eq!( trie.minimal().map(|p| p.to_string()).collect::<Vec<_>>(), ["10.0.0.0/8"]);
+
+    // disjoint hierarchies
+    let trie = Ipv4RTrieSet::from_iter(["10.0.0.0/8", "10.1.0.0/16", "10.1.1.0/24", "11.0.0.0/8", "192.168.0.0/16", "192.168.0.0/24", "172.16.1.0/24"]
+        .map(|p| p.parse().unwrap()));
+    assert_eq!( trie.minimal().map(|p| p.to_string()).collect::<Vec<_>>(), ["10.0.0.0/8", "11.0.0.0/8", "172.16.1.0/24", "192.168.0.0/16"]);
+
+    assert_eq!( Ipv4RTrieSet::new().minimal().count(), 0);
+}
+
+#[test]
+fn initial_capacities()
+{
+    let set = Ipv4RTrieSet::with_capacities(100, 300);
+    assert!( set.0.leaves.0.capacity() >= 100);
+    assert!( set.0.branching.0.capacity() >= 300);
+    assert!( set.is_empty());
+
+    let map = Ipv6RTrieMap::<u32>::with_capacities(10, 5000);
+    assert!( map.0.leaves.0.capacity() >= 10);
+    assert!( map.0.branching.0.capacity() >= 5000);
+
+    let set = Ipv4RTrieSet::with_capacity(1000);
+    assert!( set.0.branching.0.capacity() >= 500);
+}
+
+#[test]
+fn priority_lookups()
+{
+    let net = |s: &str| s.parse::<Ipv4Net>().unwrap();
+    let addr = Ipv4Addr::new(10,1,1,1);
+
+    // two equivalent prefixes, whatever the insertion order
+    for order in [[(net("10.1.0.1/16"), 5), (net("10.1.0.2/16"), 7)], [(net("10.1.0.2/16"), 7), (net("10.1.0.1/16"), 5)]] {
+        let acl = order.into_iter().collect::<RTriePriorityMap<_>>();
+        assert_eq!( acl.len().get(), 2);
+        assert_eq!( acl.iter().count(), 2);
+        assert_eq!( acl.lookup_best(&addr), Some((&net("10.1.0.2/16"), 7)));
+    }
+
+    // the highest priority wins over the longest prefix
+    let mut acl = RTriePriorityMap::new();
+    acl.insert_with_priority(net("10.0.0.0/8"), 3);
+    acl.insert_with_priority(net("10.1.1.0/24"), 2);
+    assert_eq!( acl.lookup_best(&addr), Some((&net("10.0.0.0/8"), 3)));
+    acl.insert_with_priority(net("10.1.1.0/24"), 3);
+    assert_eq!( acl.lookup_best(&addr), Some((&net("10.1.1.0/24"), 3)));
+    assert_eq!( acl.lookup_best(&Ipv4Addr::new(10,2,2,2)), Some((&net("10.0.0.0/8"), 3)));
+    assert_eq!( acl.lookup_best(&Ipv4Addr::new(11,2,2,2)), None);
+}
+
+#[test]
+fn set_from_addr_len_pairs()
+{
+    let trie = Ipv4RTrieSet::from_addr_len_iter([(Ipv4Addr::new(10,1,2,3), 16), (Ipv4Addr::new(192,168,1,0), 24), (Ipv4Addr::new(10,1,0,0), 16)]).unwrap();
+    assert_eq!( trie.iter().map(|p| p.to_string()).collect::<Vec<_>>(), ["0.0.0.0/0", "10.1.0.0/16", "192.168.1.0/24"]);
+
+    let err = Ipv4RTrieSet::from_addr_len_iter([(Ipv4Addr::new(10,1,2,3), 16), (Ipv4Addr::new(192,168,1,0), 40), (Ipv4Addr::new(10,2,0,0), 16)]);
+    assert_eq!( err.err(), Some(IpPrefixError::PrefixLenError));
+
+    let trie = Ipv6RTrieSet::from_addr_len_iter([(Ipv6Addr::new(0x2001,0xdb8,1,2,3,4,5,6), 48)]).unwrap();
+    assert_eq!( trie.iter().nth(1).unwrap().to_string(), "2001:db8:1::/48");
+    assert!( Ipv6RTrieSet::from_addr_len_iter([(Ipv6Addr::LOCALHOST, 129)]).is_err());
+}
+
+#[test]
+fn compress_and_map()
+{
+    let trie = random_ipv4_prefixes(1_000, 8..=32)
+        .enumerate()
+        .map(|(i, p)| (p, i))
+        .collect::<Ipv4RTrieMap<_>>();
+
+    let f = |k: &Ipv4Prefix, v: &usize| (k.len() as usize) * 1_000_000 + v;
+    let lctrie = trie.clone().compress_map(f);
+    assert_eq!( lctrie.len(), trie.len());
+    assert!( lctrie.iter().all(|(k, w)| *w == f(k, trie.get(k).unwrap())));
+    trie.iter().for_each(|(k, v)| assert_eq!( lctrie.get(k), Some(&f(k, v))));
+}
+
+#[test]
+fn map_differences()
+{
+    let pfx = |s: &str| s.parse::<Ipv4Prefix>().unwrap();
+    let before = Ipv4RTrieMap::from_iter([(pfx("10.0.0.0/8"), "a"), (pfx("10.1.0.0/16"), "b"), (pfx("192.168.0.0/16"), "c")]);
+    let mut after = before.clone();
+    after.insert(pfx("172.16.0.0/12"), "d");
+    after.remove(&pfx("10.1.0.0/16"));
+    after.insert(pfx("192.168.0.0/16"), "e");
+
+    let diff = before.diff(&after);
+    assert_eq!( diff.removed, [(&pfx("10.1.0.0/16"), &"b")]);
+    assert_eq!( diff.added, [(&pfx("172.16.0.0/12"), &"d")]);
+    assert_eq!( diff.changed, [(&pfx("192.168.0.0/16"), &"c", &"e")]);
+
+    let reverse = after.diff(&before);
+    assert_eq!( reverse.removed, diff.added);
+    assert_eq!( reverse.added, diff.removed);
+    assert_eq!( reverse.changed, [(&pfx("192.168.0.0/16"), &"e", &"c")]);
+
+    assert!( before.diff(&before.clone()).is_empty());
+    let mut root = before.clone();
+    root.insert(Ipv4Prefix::root(), "root");
+    assert_eq!( before.diff(&root).changed, [(&Ipv4Prefix::root(), &"", &"root")]);
+}
+
+#[test]
+fn partition_by_top_prefix()
+{
+    let pfx = |s: &str| s.parse::<Ipv4Prefix>().unwrap();
+    let trie = Ipv4RTrieSet::from_iter(["10.0.0.0/8", "10.1.0.0/16", "10.1.1.0/24", "10.200.0.0/16", "172.16.0.0/12", "192.168.1.0/24", "192.168.2.0/24"]
+        .map(pfx));
+
+    let parts = trie.partition_by_top();
+    assert_eq!( parts.len(), 4);
+    let ten = &parts[&pfx("10.0.0.0/8")];
+    assert_eq!( ten.len().get(), 5);
+    assert!( ["10.0.0.0/8", "10.1.0.0/16", "10.1.1.0/24", "10.200.0.0/16"].iter().all(|p| ten.contains(&pfx(p))));
+    assert_eq!( parts[&pfx("172.16.0.0/12")].len().get(), 2);
+    assert!( parts[&pfx("192.168.1.0/24")].contains(&pfx("192.168.1.0/24")));
+    assert!( !parts[&pfx("192.168.1.0/24")].contains(&pfx("192.168.2.0/24")));
+    assert_eq!( parts.values().map(|s| s.len().get() - 1).sum::<usize>(), trie.len().get() - 1);
+
+    assert!( Ipv4RTrieSet::new().partition_by_top().is_empty());
+}
+
+#[test]
+fn compressed_bulk_updates()
+{
+    let trie = Ipv4RTrieMap::from_iter(["10.0.0.0/8", "10.1.0.0/16", "10.1.1.0/24", "192.168.0.0/16"]
+        .map(|p| (p.parse().unwrap(), 100u32)));
+    let mut lctrie = trie.clone().compress();
+
+    lctrie.values_mut().for_each(|v| *v += 1);
+    assert!( lctrie.iter().all(|(_, v)| *v == 101 || *v == 1));
+    assert_eq!( lctrie.lookup(&Ipv4Addr::new(10,1,1,1)).1, &101);
+
+    lctrie.update_all(|k, v| *v += k.len() as u32);
+    assert!( lctrie.iter().all(|(k, v)| *v == trie.get(k).unwrap() + 1 + k.len() as u32));
+    assert_eq!( lctrie.lookup(&Ipv4Addr::new(10,1,1,1)).1, &125);
+    assert_eq!( lctrie.lookup(&Ipv4Addr::new(11,1,1,1)).1, &1);
+}
+
+#[test]
+fn aggregated_collect()
+{
+    let prefixes = ["10.0.0.0/8", "10.1.0.0/16", "10.1.1.0/24", "10.2.0.0/16",
+        "192.168.1.0/24", "192.168.1.128/25", "192.168.2.0/24", "172.16.0.0/12", "172.16.5.0/24"]
+        .map(|p| p.parse::<Ipv4Prefix>().unwrap());
+
+    let trie = Ipv4RTrieSet::from_iter_aggregated(prefixes);
+    let mut expected = Ipv4RTrieSet::from(["10.0.0.0/8", "192.168.1.0/24", "192.168.2.0/24", "172.16.0.0/12"]
+        .map(|p| p.parse::<Ipv4Prefix>().unwrap()));
+    assert_eq!( trie.len(), expected.len());
+    assert!( expected.iter().all(|p| trie.contains(p)));
+
+    // already minimal sets are kept as is
+    assert_eq!( expected.dedup_covered(), 0);
+    let trie = Ipv4RTrieSet::from_iter_aggregated(expected.iter().skip(1).cloned());
+    assert_eq!( trie.len(), expected.len());
+    assert!( expected.iter().all(|p| trie.contains(p)));
+}
+
+#[test]
+fn cursor_navigation()
+{
+    let trie = Ipv4RTrieSet::from_iter(["10.0.0.0/8", "10.1.0.0/16", "10.1.1.0/24", "10.1.2.0/24",
+        "10.2.0.0/16", "192.168.0.0/16"].map(|p| p.parse::<Ipv4Prefix>().unwrap()));
+    let prefix = |s: &str| s.parse::<Ipv4Prefix>().unwrap();
+
+    // go down from the root...
+    let root = trie.cursor_at(&Ipv4Prefix::root()).unwrap();
+    assert!( root.is_root() && root.parent().is_none() && root.next_sibling().is_none());
+    assert_eq!( root.children().map(|c| *c.prefix()).collect::<Vec<_>>(), [prefix("10.0.0.0/8"), prefix("192.168.0.0/16")]);
+
+    let top = root.first_child().unwrap();
+    assert_eq!( top.prefix(), &prefix("10.0.0.0/8"));
+    assert_eq!( top.next_sibling().unwrap().prefix(), &prefix("192.168.0.0/16"));
+    assert!( top.next_sibling().unwrap().next_sibling().is_none());
+
+    let middle = top.first_child().unwrap();
+    assert_eq!( middle.prefix(), &prefix("10.1.0.0/16"));
+    assert_eq!( middle.next_sibling().unwrap().prefix(), &prefix("10.2.0.0/16"));
+
+    let bottom = middle.first_child().unwrap();
+    assert_eq!( bottom.prefix(), &prefix("10.1.1.0/24"));
+    assert!( bottom.first_child().is_none());
+    assert_eq!( bottom.next_sibling().unwrap().prefix(), &prefix("10.1.2.0/24"));
+    assert!( bottom.next_sibling().unwrap().next_sibling().is_none());
+
+    // ...then go back up
+    assert_eq!( bottom.parent(), Some(middle));
+    assert_eq!( bottom.parent().and_then(|c| c.parent()), Some(top));
+    assert_eq!( bottom.parent().and_then(|c| c.parent()).and_then(|c| c.parent()), Some(root));
+    assert_eq!( trie.cursor_at(&prefix("10.1.2.0/24")).unwrap().parent(), Some(middle));
+
+    assert!( trie.cursor_at(&prefix("10.1.3.0/24")).is_none());
+    assert!( trie.cursor_at(&prefix("10.1.0.0/17")).is_none());
+}
+
+#[test]
+fn cursor_walk_over_random_sets()
+{
+    let prefixes = random_ipv4_prefixes(2_000, 1..=16).collect::<Vec<_>>();
+    let mut trie = Ipv4RTrieSet::from_iter(prefixes.iter().cloned());
+    prefixes.iter().step_by(3).for_each(|p| { trie.remove(p); });
+
+    // the children are the prefixes which have this one as parent
+    let mut expected = std::collections::HashMap::<Ipv4Prefix,Vec<Ipv4Prefix>>::new();
+    trie.iter()
+        .filter_map(|p| trie.cursor_at(p).unwrap().parent().map(|c| (*c.prefix(), *p)))
+        .for_each(|(parent, p)| expected.entry(parent).or_default().push(p));
+    expected.values_mut().for_each(|v| v.sort_by_key(|p| (p.network(), p.len())));
+    for p in trie.iter() {
+        let children = trie.cursor_at(p).unwrap().children().map(|c| *c.prefix()).collect::<Vec<_>>();
+        assert_eq!( children, expected.remove(p).unwrap_or_default(), "children of {}", p);
+    }
+
+    // a depth-first walk through the first children and the next siblings reaches all the prefixes
+    let mut count = 0;
+    let mut stack = vec![trie.cursor_at(&Ipv4Prefix::root()).unwrap()];
+    while let Some(c) = stack.pop() {
+        count += 1;
+        stack.extend(c.next_sibling());
+        stack.extend(c.first_child());
+    }
+    assert_eq!( count, trie.len().get());
+}
+
+#[test]
+fn iteration_without_root()
+{
+    let prefixes = ["10.0.0.0/8", "10.1.0.0/16", "1.1.1.0/24", "192.168.0.0/16", "0.0.0.0/1"]
+        .map(|p| p.parse::<Ipv4Prefix>().unwrap());
+
+    let set = Ipv4RTrieSet::from_iter(prefixes);
+    assert_eq!( set.iter_non_root().count(), prefixes.len());
+    assert_eq!( set.iter_non_root().len(), prefixes.len());
+    assert!( set.iter_non_root().all(|p| prefixes.contains(p)));
+    assert!( Ipv4RTrieSet::new().iter_non_root().next().is_none());
+
+    let map = Ipv4RTrieMap::from_iter(prefixes.map(|p| (p, p.len())));
+    assert_eq!( map.iter_non_root().count(), prefixes.len());
+    assert!( map.iter_non_root().all(|(p, len)| prefixes.contains(p) && p.len() == *len));
+
+    // even when explicitly inserted, the root prefix is not iterated
+    let mut set = set;
+    set.insert(Ipv4Prefix::root());
+    assert_eq!( set.iter_non_root().count(), prefixes.len());
+}
+
+#[test]
+fn batch_removals()
+{
+    let prefix = |s: &str| s.parse::<Ipv4Prefix>().unwrap();
+    let present = ["10.0.0.0/8", "10.1.0.0/16", "10.1.1.0/24", "192.168.0.0/16"].map(prefix);
+    let absent = ["10.2.0.0/16", "10.1.0.0/17", "172.16.0.0/12"].map(prefix);
+
+    let mut set = Ipv4RTrieSet::from_iter(present);
+    let batch = [present[1], absent[0], present[3], absent[1], Ipv4Prefix::root(), present[1], absent[2]];
+    assert_eq!( set.remove_all(batch), 2);
+    assert_eq!( set.len().get(), 3);
+    assert!( set.contains(&present[0]) && set.contains(&present[2]));
+    assert!( set.contains(&Ipv4Prefix::root()));
+    set.0.debug_validate();
+    assert_eq!( set.remove_all(absent), 0);
+    assert_eq!( set.remove_all(present), 2);
+    assert!( set.is_empty());
+
+    let mut map = Ipv4RTrieMap::from_iter(present.map(|p| (p, p.len())));
+    assert_eq!( map.remove_all(batch), 2);
+    assert_eq!( map.len().get(), 3);
+    assert_eq!( map.get(&present[2]), Some(&24));
+    assert_eq!( map.lookup(&prefix("10.1.2.0/24")), (&present[0], &8));
+}
+
+#[test]
+fn branching_bit_paths()
+{
+    let prefixes = ["10.0.0.0/8", "10.1.0.0/16", "10.1.1.0/24", "10.128.0.0/9", "11.0.0.0/8", "192.168.0.0/16"]
+        .map(|p| p.parse::<Ipv4Prefix>().unwrap());
+    let trie = Ipv4RTrieSet::from_iter(prefixes);
+
+    // the root prefix is the escape of the root branching
+    assert_eq!( trie.0.bit_path(&Ipv4Prefix::root()), [1]);
+
+    for p in prefixes {
+        let path = trie.0.bit_path(&p);
+        let (last, descent) = path.split_last().unwrap();
+        assert_eq!( path[0], 1, "{p}: {path:?}");
+        assert!( path.windows(2).all(|w| w[0] < w[1]), "{p}: {path:?}");
+        // all the traversed bits belong to the prefix
+        assert!( descent.iter().all(|&bit| bit <= p.len()), "{p}: {path:?}");
+        assert!( *last <= p.len() + 1, "{p}: {path:?}");
+        assert!( path.len() <= prefixes.len());
+    }
+
+    // 10.0.0.0/8 and 11.0.0.0/8 only differ by their 8th bit
+    // and 10.0.0.0/8 is the escape of the branching which splits 10.1.0.0/16 and 10.128.0.0/9
+    assert_eq!( trie.0.bit_path(&prefixes[4]), [1, 8]);
+    assert_eq!( trie.0.bit_path(&prefixes[0]), [1, 8, 9]);
+    assert_eq!( trie.0.bit_path(&prefixes[3]), [1, 8, 9]);
+    assert_eq!( trie.0.bit_path(&prefixes[5]), [1]);
+
+    // a more specific prefix is reached through its covering one
+    assert_eq!( trie.0.bit_path(&prefixes[1]), [1, 8, 9, 17]);
+    assert_eq!( trie.0.bit_path(&prefixes[2]), [1, 8, 9, 17]);
+
+    // an absent key leads to its longest prefix match
+    let absent = "10.1.1.128/25".parse::<Ipv4Prefix>().unwrap();
+    assert_eq!( trie.0.bit_path(&absent), trie.0.bit_path(&prefixes[2]));
+    let absent = "172.16.0.0/12".parse::<Ipv4Prefix>().unwrap();
+    assert_eq!( trie.0.bit_path(&absent), [1]);
+}
+
+#[test]
+fn json_round_trip()
+{
+    let trie = Ipv4RTrieSet::from_iter(["10.0.0.0/8", "10.1.0.0/16", "1.1.1.0/24", "192.168.0.0/16"]
+        .map(|p| p.parse::<Ipv4Prefix>().unwrap()));
+
+    let mut buf = Vec::new();
+    trie.write_json(&mut buf).unwrap();
+    let json = String::from_utf8(buf).unwrap();
+    let read = Ipv4RTrieSet::from_json(&json).unwrap();
+    assert_eq!( read.len(), trie.len());
+    assert!( trie.iter().all(|p| read.contains(p)));
+
+    let mut buf = Vec::new();
+    Ipv6RTrieSet::new().write_json(&mut buf).unwrap();
+    assert_eq!( buf, b"[]");
+    assert!( Ipv6RTrieSet::from_json(" [ ] ").unwrap().is_empty());
+
+    let read = Ipv6RTrieSet::from_json("[\n  \"2001:db8::/32\",\n  \"2001:db8:1::/48\"\n]\n").unwrap();
+    assert_eq!( read.len().get(), 3);
+
+    for bad in ["", "[", "\"10.0.0.0/8\"", "[10.0.0.0/8]", "[\"10.0.0.0/8\",]", "[\"10.0.0.0/8\" \"1.0.0.0/8\"]", "[\"10.0.\\\"0.0/8\"]"] {
+        assert_eq!( Ipv4RTrieSet::from_json(bad).err(), Some(JsonError::Syntax), "{bad}");
+    }
+    assert_eq!( Ipv4RTrieSet::from_json("[\"2001:db8::/32\"]").err(), Some(JsonError::Prefix(IpPrefixError::AddrParseError)));
+}
+
+#[test]
+fn insertion_events()
+{
+    let prefix = |s: &str| s.parse::<Ipv4Prefix>().unwrap();
+    let mut trie = Ipv4RTrieSet::new();
+    let mut events = vec![];
+    let mut insert = |trie: &mut Ipv4RTrieSet, p: &str| trie.insert_reporting(prefix(p), |e| events.push(e));
+
+    insert(&mut trie, "10.1.0.0/16");
+    insert(&mut trie, "10.1.2.0/24");
+    insert(&mut trie, "10.1.2.128/25");
+    insert(&mut trie, "10.2.0.0/16");
+    insert(&mut trie, "10.0.0.0/8");
+    insert(&mut trie, "10.1.2.0/24");
+    assert_eq!( events, [
+        InsertEvent::New,
+        InsertEvent::ShadowedBy(prefix("10.1.0.0/16")),
+        InsertEvent::ShadowedBy(prefix("10.1.2.0/24")),
+        InsertEvent::New,
+        InsertEvent::New,
+        InsertEvent::ReplacedEquivalent(prefix("10.1.2.0/24")),
+    ]);
+    assert_eq!( trie.len().get(), 6);
+
+    // the replaced prefix is the stored one (with its host bits)
+    let mut trie = RTrieSet::<Ipv4Net>::new();
+    let mut events = vec![];
+    ["1.1.1.1/24", "1.1.1.2/24", "1.1.1.2/28"].iter()
+        .for_each(|p| trie.insert_reporting(p.parse().unwrap(), |e| events.push(e)));
+    assert_eq!( events, [
+        InsertEvent::New,
+        InsertEvent::ReplacedEquivalent("1.1.1.1/24".parse().unwrap()),
+        InsertEvent::ShadowedBy("1.1.1.2/24".parse().unwrap()),
+    ]);
+}
+
+#[test]
+fn bounded_compression()
+{
+    let trie = random_ipv4_prefixes(1000, 8..=32).collect::<Ipv4RTrieSet>();
+    let needed = trie.estimated_compressed_bytes();
+
+    // too small, even by a single byte
+    assert_eq!( LCTrieSet::build_with_arena_capacity(trie.clone(), 0).err(), Some(CapacityError));
+    assert_eq!( LCTrieSet::build_with_arena_capacity(trie.clone(), needed - 1).err(), Some(CapacityError));
+
+    // exactly enough or more
+    let reference = trie.clone().compress();
+    for arena in [needed, 2 * needed] {
+        let lctrie = LCTrieSet::build_with_arena_capacity(trie.clone(), arena).unwrap();
+        assert_eq!( lctrie.memory_bytes(), needed);
+        assert!( lctrie.iter().all(|p| lctrie.get(p) == Some(p)));
+        assert!( lctrie.iter().eq(reference.iter()));
+        random_ipv4_addrs(1000)
+            .for_each(|a| assert_eq!( lctrie.lookup(&a), reference.lookup(&a)));
+    }
+
+    // the leaves are not shrunk, so their whole capacity is counted
+    // (a clone would be shrunk)
+    let spare = || {
+        let mut spare = trie.clone();
+        spare.0.leaves.0.reserve(100);
+        spare
+    };
+    let extra = (spare().0.leaves.0.capacity() - trie.0.leaves.len()) * std::mem::size_of::<trie::common::Leaf<Ipv4Prefix,()>>();
+    assert_eq!( LCTrieSet::build_with_arena_capacity(spare(), needed).err(), Some(CapacityError));
+    assert!( LCTrieSet::build_with_arena_capacity(spare(), needed + extra).unwrap().iter().eq(reference.iter()));
+
+    // a bounded memzone fails rather than reallocating
+    use trie::common::{BranchingIndex, LeafIndex};
+    let mut tree = trie::lctrie::CompressedTree::bounded(0);
+    assert_eq!( tree.push(BranchingIndex::root(), LeafIndex::root_leaf(), 0, 1).err(), Some(CapacityError));
+}
+
+#[test]
+fn get_or_insert_values()
+{
+    let prefix = |s: &str| s.parse::<Ipv4Prefix>().unwrap();
+    let mut trie = Ipv4RTrieMap::with_root(0u32);
+    let mut calls = 0;
+
+    *trie.get_or_insert_with_mut(prefix("10.0.0.0/8"), || { calls += 1; 100 }) += 1;
+    *trie.get_or_insert_with_mut(prefix("10.0.0.0/8"), || { calls += 1; 200 }) += 1;
+    assert_eq!( calls, 1);
+    assert_eq!( trie.get(&prefix("10.0.0.0/8")), Some(&102));
+
+    // the root prefix is always present
+    *trie.get_or_insert_with_mut(Ipv4Prefix::root(), || unreachable!()) = 7;
+    assert_eq!( trie.lookup(&prefix("11.0.0.0/8")).1, &7);
+
+    // as a counter, consistent with the usual insertions
+    // (each prefix is counted several times)
+    let samples = random_ipv4_prefixes(500, 8..=32).collect::<Vec<_>>();
+    let mut trie = Ipv4RTrieMap::with_root(0u32);
+    let mut expected = Ipv4RTrieMap::with_root(0u32);
+    samples.iter().cycle().take(2000).for_each(|&p| {
+        *trie.get_or_insert_with_mut(p, || 0) += 1;
+        let count = expected.get(&p).copied().unwrap_or(0);
+        expected.insert(p, count + 1);
+    });
+    trie.0.debug_validate();
+    assert_eq!( trie.0.check_invariants(), Ok(()));
+    assert_eq!( trie.len(), expected.len());
+    assert!( expected.iter().all(|(p, count)| trie.get(p) == Some(count)));
+}
+
+#[test]
+fn escape_chains()
+{
+    let prefix = |a: u32, len: u8| Ipv4Prefix::new(Ipv4Addr::from(a), len).unwrap();
+
+    // a comb of more specific prefixes under a supernet: all the branching share its escape
+    let comb = (9..=32u8).map(|l| prefix(0x0a00_0000 | (1 << (32-l)), l))
+        .chain([prefix(0x0a00_0000, 8)])
+        .collect::<Ipv4RTrieSet>();
+    assert_eq!( comb.max_escape_chain(), 23);
+
+    // the same comb without the supernet falls back to the root prefix
+    let comb = (9..=32u8).map(|l| prefix(0x0a00_0000 | (1 << (32-l)), l)).collect::<Ipv4RTrieSet>();
+    assert!( comb.max_escape_chain() >= 23);
+
+    // a flat set of disjoint prefixes is balanced
+    let flat = (0..256u32).map(|i| prefix(i << 24, 8)).collect::<Ipv4RTrieSet>();
+    assert_eq!( flat.max_escape_chain(), 8);
+
+    // nested prefixes are the escapes of successive branching
+    let nested = (8..=32u8).map(|l| prefix(0x0a00_0000, l)).collect::<Ipv4RTrieSet>();
+    assert_eq!( nested.max_escape_chain(), 1);
+
+    assert_eq!( Ipv4RTrieSet::new().max_escape_chain(), 1);
+}
+
+#[test]
+fn distinct_next_hops()
+{
+    let hops = [Ipv4Addr::new(192,0,2,1), Ipv4Addr::new(192,0,2,2), Ipv4Addr::new(192,0,2,3)];
+    let mut trie = Ipv4RTrieMap::with_root(hops[0]);
+    assert_eq!( trie.distinct_values(), 1);
+
+    (0..100u32).for_each(|i| { trie.insert(Ipv4Prefix::new(Ipv4Addr::from(i << 16), 16).unwrap(), hops[(i % 2) as usize]); });
+    assert_eq!( trie.distinct_values(), 2);
+
+    trie.insert("10.0.0.0/8".parse().unwrap(), hops[2]);
+    assert_eq!( trie.distinct_values(), 3);
+    assert_eq!( trie.distinct_values(), trie.group_by_value().len());
+
+    // the root prefix counts as any other one
+    *trie.lookup_mut(&Ipv4Prefix::root()).1 = hops[2];
+    assert_eq!( trie.distinct_values(), 3);
+    trie.remove(&"10.0.0.0/8".parse::<Ipv4Prefix>().unwrap());
+    assert_eq!( trie.distinct_values(), 3);
+}
+
+#[test]
+fn layered_lookups()
+{
+    let prefix = |s: &str| s.parse::<Ipv4Prefix>().unwrap();
+    let specific = Ipv4RTrieSet::from_iter(["10.1.0.0/16", "10.1.1.0/24", "172.16.0.0/12"].map(prefix));
+    let default = Ipv4RTrieSet::from_iter(["10.0.0.0/8", "10.1.1.0/24", "192.168.0.0/16", "192.168.1.0/24"].map(prefix));
+
+    // the specific table wins, even with a shorter prefix
+    assert_eq!( specific.lookup_or(&Ipv4Addr::new(10,1,1,1), &default), &prefix("10.1.1.0/24"));
+    assert_eq!( specific.lookup_or(&Ipv4Addr::new(10,1,2,1), &default), &prefix("10.1.0.0/16"));
+    assert_eq!( specific.lookup_or(&prefix("172.16.1.0/24"), &default), &prefix("172.16.0.0/12"));
+
+    // the prefixes only present in the fallback table
+    assert_eq!( specific.lookup_or(&Ipv4Addr::new(10,2,1,1), &default), &prefix("10.0.0.0/8"));
+    assert_eq!( specific.lookup_or(&Ipv4Addr::new(192,168,1,1), &default), &prefix("192.168.1.0/24"));
+    assert_eq!( specific.lookup_or(&prefix("192.168.0.0/16"), &default), &prefix("192.168.0.0/16"));
+
+    // no match at all
+    assert_eq!( specific.lookup_or(&Ipv4Addr::new(1,1,1,1), &default), &Ipv4Prefix::root());
+    assert_eq!( specific.lookup_or(&Ipv4Addr::new(1,1,1,1), &Ipv4RTrieSet::new()), &Ipv4Prefix::root());
+}